@@ -0,0 +1,207 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use crate::gitlab_tools::{GitLabEvent, GitLabIssue, GitLabMR, GitLabTools, GitLabUser};
+use crate::util::truncate;
+
+/// Days of events covered by `/summary` unless another window is given
+pub const DEFAULT_DAYS: i64 = 7;
+/// Assigned items without updates for this many days are flagged as stale
+pub const STALE_DAYS: i64 = 14;
+/// Items listed individually in each section of the report; the rest are only counted
+const MAX_ITEMS_SHOWN: usize = 10;
+
+/// Everything `/summary` knows about one developer, fetched from GitLab up front
+pub struct DeveloperActivity {
+    pub user: GitLabUser,
+    pub days: i64,
+    pub issues: Vec<GitLabIssue>,
+    pub mrs: Vec<GitLabMR>,
+    pub reviews: Vec<GitLabMR>,
+    pub events: Vec<GitLabEvent>,
+    /// Stale items, issues blocked by open issues and MRs with conflicts
+    pub blockers: Vec<String>,
+}
+
+impl DeveloperActivity {
+    pub async fn collect(tools: &GitLabTools, username: &str, days: i64) -> Result<Self> {
+        let user = tools.get_user(username).await?;
+        let now = Utc::now();
+
+        let (issues, mrs, reviews, events) = tokio::try_join!(
+            tools.get_assigned_issues(&user.username),
+            tools.get_assigned_mrs(&user.username),
+            tools.get_review_requests(&user.username),
+            tools.get_user_events(&user, now - Duration::days(days)),
+        )?;
+        let links = futures::future::join_all(issues.iter().map(|issue| tools.get_open_blockers(issue.iid))).await;
+
+        // A failed links fetch only leaves that issue's blockers unknown; the rest of the summary stands
+        let mut blockers = Vec::new();
+        let mut unchecked = Vec::new();
+        for (issue, blocked_by) in issues.iter().zip(&links) {
+            match blocked_by {
+                Ok(blocked_by) => {
+                    for link in blocked_by {
+                        blockers.push(format!("#{} is blocked by open issue #{}: {}", issue.iid, link.iid, link.title));
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("{:#}", e);
+                    unchecked.push(format!("#{}", issue.iid));
+                }
+            }
+        }
+        if !unchecked.is_empty() {
+            let more = if unchecked.len() > 5 { format!(" and {} more", unchecked.len() - 5) } else { String::new() };
+            blockers.push(format!("Blocking issues unknown for {}{}: their links could not be fetched",
+                unchecked[..unchecked.len().min(5)].join(", "), more));
+        }
+        for mr in &mrs {
+            if mr.has_conflicts {
+                blockers.push(format!("!{} has merge conflicts", mr.iid));
+            }
+        }
+        let stale = issues.iter().map(|i| (format!("#{}", i.iid), &i.updated_at))
+            .chain(mrs.iter().map(|mr| (format!("!{}", mr.iid), &mr.updated_at)));
+        for (reference, updated_at) in stale {
+            if let Some(idle) = days_since(updated_at, now).filter(|idle| *idle >= STALE_DAYS) {
+                blockers.push(format!("{} has had no activity for {} days", reference, idle));
+            }
+        }
+
+        Ok(Self { user, days, issues, mrs, reviews, events, blockers })
+    }
+
+    /// The facts the summary is based on, the same on every run
+    pub fn report(&self) -> String {
+        let now = Utc::now();
+        let name = if self.user.name.is_empty() { String::new() } else { format!(" ({})", self.user.name) };
+        let mut text = format!("👤 @{}{}, last {} days\n", self.user.username, name, self.days);
+
+        text.push_str(&format!("\n📋 Assigned issues ({})\n", self.issues.len()));
+        for issue in self.issues.iter().take(MAX_ITEMS_SHOWN) {
+            let labels = if issue.labels.is_empty() { String::new() } else { format!(" [{}]", issue.labels.join(", ")) };
+            text.push_str(&format!("  - #{} {}{}{}\n", issue.iid, issue.title, labels, updated(&issue.updated_at, now)));
+        }
+        text.push_str(&more(self.issues.len()));
+
+        text.push_str(&format!("\n🔀 Assigned merge requests ({})\n", self.mrs.len()));
+        for mr in self.mrs.iter().take(MAX_ITEMS_SHOWN) {
+            let draft = if mr.draft { " [draft]" } else { "" };
+            text.push_str(&format!("  - !{} {}{} ({} → {}){}\n",
+                mr.iid, mr.title, draft, mr.source_branch, mr.target_branch, updated(&mr.updated_at, now)));
+        }
+        text.push_str(&more(self.mrs.len()));
+
+        text.push_str(&format!("\n👀 Awaiting their review ({})\n", self.reviews.len()));
+        for mr in self.reviews.iter().take(MAX_ITEMS_SHOWN) {
+            text.push_str(&format!("  - !{} {} by @{}{}\n", mr.iid, mr.title, mr.author.username, updated(&mr.updated_at, now)));
+        }
+        text.push_str(&more(self.reviews.len()));
+
+        text.push_str(&format!("\n📅 Recent activity ({} events)\n", self.events.len()));
+        if !self.events.is_empty() {
+            text.push_str(&format!("  {}\n", self.event_counts()));
+        }
+        for event in self.events.iter().take(MAX_ITEMS_SHOWN) {
+            text.push_str(&format!("  - {} {}\n", event.created_at.get(..10).unwrap_or(&event.created_at), describe(event)));
+        }
+        text.push_str(&more(self.events.len()));
+
+        text.push_str(&format!("\n⚠️  Potential blockers ({})\n", self.blockers.len()));
+        for blocker in self.blockers.iter().take(MAX_ITEMS_SHOWN) {
+            text.push_str(&format!("  - {}\n", blocker));
+        }
+        text.push_str(&more(self.blockers.len()));
+        text
+    }
+
+    /// Prompt for the written summary. The report is the only source, so the model has nothing to look up.
+    pub fn summary_prompt(&self) -> String {
+        let mut events = String::new();
+        for event in &self.events {
+            events.push_str(&format!("- {} {}\n", event.created_at, describe(event)));
+        }
+        format!(
+            "Write a concise summary (at most 6 sentences) of what @{username} is working on, for a team lead. \
+             Cover their focus, recent progress, what is waiting on them, and any blockers. \
+             Use only the data below and do not call any tools.\n\n{report}\nAll events:\n{events}",
+            username = self.user.username,
+            report = self.report(),
+            events = if events.is_empty() { "(none)\n".to_string() } else { events },
+        )
+    }
+
+    /// e.g. "3 commented on, 2 pushed to (5 commits), 1 closed"
+    fn event_counts(&self) -> String {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for event in &self.events {
+            match counts.iter_mut().find(|(action, _)| *action == event.action_name) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((&event.action_name, 1)),
+            }
+        }
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        let commits: u64 = self.events.iter().filter_map(|e| e.push_data.as_ref()).map(|p| p.commit_count).sum();
+        counts.iter()
+            .map(|(action, count)| {
+                if action.starts_with("pushed") && commits > 0 {
+                    format!("{} {} ({} commits)", count, action, commits)
+                } else {
+                    format!("{} {}", count, action)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// One line per event, e.g. "commented on Issue #12: Looks like the cache again"
+fn describe(event: &GitLabEvent) -> String {
+    if let Some(push) = &event.push_data {
+        let what = push.commit_title.as_deref().map(|title| truncate(title, 80)).unwrap_or_default();
+        return format!("{} {} {}: {}",
+            event.action_name, push.ref_type.as_deref().unwrap_or("branch"), push.ref_name.as_deref().unwrap_or("?"), what);
+    }
+    if let Some(note) = &event.note {
+        let target = reference(note.noteable_type.as_deref(), note.noteable_iid);
+        return format!("{} {}: {}", event.action_name, target, truncate(note.body.lines().next().unwrap_or(""), 80));
+    }
+    let target = reference(event.target_type.as_deref(), event.target_iid);
+    format!("{} {}: {}", event.action_name, target, event.target_title.as_deref().unwrap_or(""))
+}
+
+/// "Issue #12", "MergeRequest !5" or just the type when there is no iid
+fn reference(target_type: Option<&str>, iid: Option<u64>) -> String {
+    let target_type = target_type.unwrap_or("item");
+    match iid {
+        Some(iid) if target_type == "MergeRequest" => format!("{} !{}", target_type, iid),
+        Some(iid) => format!("{} #{}", target_type, iid),
+        None => target_type.to_string(),
+    }
+}
+
+/// "  ... and N more" for a section cut off at `MAX_ITEMS_SHOWN`
+fn more(total: usize) -> String {
+    if total > MAX_ITEMS_SHOWN {
+        format!("  ... and {} more\n", total - MAX_ITEMS_SHOWN)
+    } else {
+        String::new()
+    }
+}
+
+fn days_since(timestamp: &str, now: DateTime<Utc>) -> Option<i64> {
+    let time = DateTime::parse_from_rfc3339(timestamp).ok()?;
+    Some(now.signed_duration_since(time.with_timezone(&Utc)).num_days())
+}
+
+/// ", updated 3 days ago", or nothing if the timestamp is missing
+fn updated(timestamp: &str, now: DateTime<Utc>) -> String {
+    match days_since(timestamp, now) {
+        Some(0) => ", updated today".to_string(),
+        Some(1) => ", updated yesterday".to_string(),
+        Some(days) => format!(", updated {} days ago", days),
+        None => String::new(),
+    }
+}
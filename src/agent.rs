@@ -1,109 +1,237 @@
-use rig::agent::Agent;
-use rig::agent::AgentBuilder;
-use rig::client::CompletionClient;
-use rig::providers::openai;
+use anyhow::Result;
+use futures::StreamExt;
+use rig::client::builder::{BoxAgent, BoxAgentBuilder};
+use rig::client::completion::CompletionClientDyn;
+use rig::providers::{anthropic, deepseek, gemini, groq, mistral, ollama, openai, openrouter};
+use rig::completion::{AssistantContent, Message, ToolDefinition};
+use rig::message::{ToolResultContent, UserContent};
+use rig::streaming::StreamingChat;
+use rig::tool::{McpTool, Tool, ToolDyn, ToolError};
+use rig::OneOrMany;
 use mcp_core::types::ToolsListResponse;
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use crate::audit::{self, Action, Outcome, Source};
+use crate::config::LlmConfig;
+use crate::conversation::{estimate_tokens, ToolCall};
+use crate::prompts::{self, PromptKind};
+
+/// Provider-agnostic agent handle; every backend is driven through the same type
+pub type LlmAgent = BoxAgent<'static>;
+
+/// Tool calls made by an agent, shared with the session that records them
+pub type ToolLog = Arc<Mutex<Vec<ToolCall>>>;
+
+/// Characters of tool output kept in the session record
+const TOOL_OUTPUT_PREVIEW: usize = 500;
+/// Characters of tool arguments shown in progress lines
+const TOOL_ARGS_PREVIEW: usize = 80;
+/// Rounds of tool calls allowed for one answer before giving up
+const MAX_TOOL_ROUNDS: usize = 10;
+/// Name prefixes of gitlab-mcp tools that only read data
+pub const READ_ONLY_PREFIXES: [&str; 6] = ["get_", "list_", "search_", "download_", "verify_", "my_"];
+/// Read-only gitlab-mcp tools whose names don't follow the prefixes
+pub const READ_ONLY_TOOLS: [&str; 1] = ["mr_discussions"];
+
+/// What happens when the agent calls a tool that can change GitLab data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolMode {
+    /// Show the exact arguments and run the call only once the user approves it
+    Confirm,
+    /// Record the call without running it (`ken --dry-run`)
+    DryRun,
+}
+
+/// Whether an MCP tool only reads. Tools not known to be read-only count as mutating.
+/// A server's `readOnlyHint` can mark a tool as mutating but never exempts one from approval.
+pub fn is_read_only(tool: &mcp_core::types::Tool) -> bool {
+    let by_name = READ_ONLY_PREFIXES.iter().any(|prefix| tool.name.starts_with(prefix))
+        || READ_ONLY_TOOLS.contains(&tool.name.as_str());
+    by_name && tool.annotations.as_ref().and_then(|a| a.read_only_hint) != Some(false)
+}
+
+/// LLM backends ken can build an agent for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    /// Any OpenAI-compatible server (vLLM, llama.cpp, LiteLLM, ...)
+    OpenAI,
+    Anthropic,
+    Ollama,
+    Gemini,
+    Groq,
+    DeepSeek,
+    OpenRouter,
+    Mistral,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 8] = [
+        Provider::OpenAI,
+        Provider::Anthropic,
+        Provider::Ollama,
+        Provider::Gemini,
+        Provider::Groq,
+        Provider::DeepSeek,
+        Provider::OpenRouter,
+        Provider::Mistral,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "openai",
+            Provider::Anthropic => "anthropic",
+            Provider::Ollama => "ollama",
+            Provider::Gemini => "gemini",
+            Provider::Groq => "groq",
+            Provider::DeepSeek => "deepseek",
+            Provider::OpenRouter => "openrouter",
+            Provider::Mistral => "mistral",
+        }
+    }
+
+    fn default_base_url(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "http://llm-api.fixstars.com/",
+            Provider::Anthropic => "https://api.anthropic.com",
+            Provider::Ollama => "http://localhost:11434",
+            Provider::Gemini => "https://generativelanguage.googleapis.com",
+            Provider::Groq => "https://api.groq.com/openai/v1",
+            Provider::DeepSeek => "https://api.deepseek.com",
+            Provider::OpenRouter => "https://openrouter.ai/api/v1",
+            Provider::Mistral => "https://api.mistral.ai",
+        }
+    }
+
+    fn default_model(self) -> &'static str {
+        match self {
+            Provider::OpenAI => "Qwen/Qwen3-32B",
+            Provider::Anthropic => anthropic::CLAUDE_3_7_SONNET,
+            Provider::Ollama => ollama::LLAMA3_2,
+            Provider::Gemini => gemini::completion::GEMINI_2_0_FLASH,
+            Provider::Groq => "llama-3.3-70b-versatile",
+            Provider::DeepSeek => deepseek::DEEPSEEK_CHAT,
+            Provider::OpenRouter => openrouter::CLAUDE_3_7_SONNET,
+            Provider::Mistral => mistral::MISTRAL_LARGE,
+        }
+    }
+
+    fn client(self, api_key: &str, base_url: &str) -> Box<dyn CompletionClientDyn> {
+        match self {
+            Provider::OpenAI => Box::new(openai::Client::from_url(api_key, base_url)),
+            Provider::Anthropic => Box::new(anthropic::ClientBuilder::new(api_key).base_url(base_url).build()),
+            Provider::Ollama => Box::new(ollama::Client::from_url(base_url)),
+            Provider::Gemini => Box::new(gemini::Client::from_url(api_key, base_url)),
+            Provider::Groq => Box::new(groq::Client::from_url(api_key, base_url)),
+            Provider::DeepSeek => Box::new(deepseek::Client::from_url(api_key, base_url)),
+            Provider::OpenRouter => Box::new(openrouter::Client::from_url(api_key, base_url)),
+            Provider::Mistral => Box::new(mistral::Client::from_url(api_key, base_url)),
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Provider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_lowercase();
+        Provider::ALL.into_iter()
+            .find(|provider| provider.name() == name)
+            .ok_or_else(|| anyhow::anyhow!(
+                "Unsupported LLM provider '{}'. Supported providers: {}",
+                s,
+                Provider::ALL.map(Provider::name).join(", ")
+            ))
+    }
+}
+
+/// The answer part of a reply from a reasoning model, without its `<think>` block
+pub fn strip_reasoning(reply: &str) -> &str {
+    reply.rsplit_once("</think>").map_or(reply, |(_, answer)| answer).trim()
+}
 
 pub struct AgentConfig{
-    api_key: String,
-    base_url: String,
-    model_name: String,
-    prompt: String,
-    temperature: f64,
-    max_tokens: u64,
+    pub provider: Provider,
+    pub api_key: String,
+    pub base_url: String,
+    pub model_name: String,
+    pub prompt: String,
+    pub temperature: f64,
+    pub max_tokens: u64,
+    pub context_window: usize,
 }
 
 pub struct KenAgent;
 
 impl AgentConfig{
-    pub fn default() -> Self{
+    /// Built-in settings for `provider`
+    pub fn for_provider(provider: Provider) -> Self {
         AgentConfig{
-            model_name: String::from("Qwen/Qwen3-32B"),
-            base_url: String::from("http://llm-api.fixstars.com/"),
+            provider,
+            model_name: provider.default_model().to_string(),
+            base_url: provider.default_base_url().to_string(),
             api_key: String::from(""),
-            prompt: Self::default_prompt(),
+            prompt: prompts::load(PromptKind::System).text,
             max_tokens: 4000,
             temperature: 0.3,
+            context_window: 32_768,
         }
     }
 
-    fn default_prompt() -> String {
-        r#"You are Ken, an AI assistant specialized in GitLab project management.
-
-Your primary responsibilities:
-- Help users query and manage GitLab issues, merge requests, and projects
-- Provide insights about project activity, team workloads, and development status
-- Use GitLab tools to fetch real-time data and perform actions
-- Suggest actionable next steps and best practices
-
-Tool Usage Guidelines:
-- **ALWAYS use GitLab MCP tools for fresh data** - never rely only on cached context
-- For issue queries, use `list_issues` with scope='all' for all issues, or specific assignee filters
-- For merge request queries, use `list_merge_requests` with proper filtering options  
-- For user/team queries, use `list_project_members` and `get_users` tools
-- Use project context to understand labels, members, and milestones
-- When creating content, use `create_issue` or `create_merge_request` tools
-- **When asked to analyze workload, you MUST call the actual MCP tools, not use cached data**
-
-Critical Tool Parameters:
-- list_issues: ALWAYS use scope='all' to see all project issues, then filter by assignee
-- list_merge_requests: Use assignee filters to get MRs per person
-- list_project_members: Get full user details including names and roles
-
-Query Intent Recognition:
-- "show/list/find issues" → Use list_issues with appropriate filters
-- "issue #123" or "tell me about issue" → Use get_issue
-- "my issues" or "assigned to me" → Use my_issues  
-- "merge requests" or "MRs" → Use list_merge_requests
-- "who is working on" → Use get_issue or list_project_members
-- "create issue/bug/feature" → Use create_issue
-- "project members/team" → Use list_project_members
-- "workload/analyze team" → Use list_project_members + list_issues (scope='all') + list_merge_requests
-- "workload distribution" → 
-  1. Get all members with list_project_members (get full names + roles)
-  2. For each member, use list_issues with scope='all' and assignee=username filter  
-  3. For each member, use list_merge_requests with assignee=username filter
-  4. Count and calculate load scores
-
-When responding:
-- Be concise and actionable
-- Format GitLab data clearly (use bullet points, tables when appropriate)  
-- Include relevant issue/MR numbers, assignees, and states
-- Always base responses on fresh tool data, not assumptions
-- If a tool call fails, explain what went wrong and suggest alternatives
-
-Workload Analysis Format:
-- Create a table showing: Full Name (username) | Role | Open Issues | Open MRs | Load Score | Status
-- Use FULL NAMES from list_project_members, not just usernames
-- Calculate Load Score as: (Issues * 1) + (MRs * 2)  
-- Status: 🔴 High (>8), 🟡 Medium (4-8), 🟢 Low (<4)
-- Sort by Load Score (highest first)
-- ONLY show members who actually have assigned work (Issues > 0 OR MRs > 0)
-- Add summary with recommendations and unassigned work count
-
-If you need to understand the user's intent better, ask specific clarifying questions."#.to_string()
+    /// Fill in the `[llm]` settings over the chosen provider's defaults
+    pub fn from_llm(llm: &LlmConfig) -> Result<Self> {
+        let provider = match &llm.provider {
+            Some(provider) => provider.parse()?,
+            None => Provider::OpenAI,
+        };
+        let defaults = Self::for_provider(provider);
+        
+        Ok(AgentConfig {
+            provider,
+            api_key: llm.api_key.clone().unwrap_or(defaults.api_key),
+            base_url: llm.base_url.clone().unwrap_or(defaults.base_url),
+            model_name: llm.model.clone().unwrap_or(defaults.model_name),
+            prompt: defaults.prompt,
+            temperature: llm.temperature.unwrap_or(defaults.temperature),
+            max_tokens: llm.max_tokens.unwrap_or(defaults.max_tokens),
+            context_window: llm.context_window.unwrap_or(defaults.context_window),
+        })
+    }
+
+    /// Tokens left for chat history and the current query once the system prompt and reply are reserved
+    pub fn history_budget(&self) -> usize {
+        self.context_window
+            .saturating_sub(self.max_tokens as usize)
+            .saturating_sub(estimate_tokens(&self.prompt))
     }
 }
 
 
 impl KenAgent{
-    pub fn default() -> Agent<openai::CompletionModel> {
-        let config = AgentConfig::default();
-        Self::get_agent(&config)
+    pub fn build(config: &AgentConfig) -> LlmAgent {
+        Self::builder(config, &config.prompt).build()
     }
 
     pub fn with_mcp_tools(
-        gitlab_config: &crate::config::Config,
+        config: &AgentConfig,
+        profile: &str,
+        project_id: Option<&str>,
         mcp_client: &crate::mcp_client::MCPClient,
         tools: ToolsListResponse,
-    ) -> Agent<openai::CompletionModel> {
-        let config = AgentConfig::default();
-        let model = openai::Client::from_url(&config.api_key, &config.base_url)
-            .completion_model(&config.model_name);
-        
+        log: ToolLog,
+        mode: ToolMode,
+    ) -> LlmAgent {
         // Build the prompt with project context if available
         let mut enhanced_prompt = config.prompt.clone();
-        if let Some(project_id) = &gitlab_config.default_project_id {
+        if let Some(project_id) = project_id {
             enhanced_prompt.push_str(&format!("\n\n## Current GitLab Project\nProject: {}\n", project_id));
             
             // Add available MCP tools info
@@ -116,28 +244,205 @@ impl KenAgent{
             }
         }
         
-        let builder = AgentBuilder::new(model)
-            .preamble(&enhanced_prompt)
-            .temperature(config.temperature)
-            .max_tokens(config.max_tokens);
+        let builder = Self::builder(config, &enhanced_prompt);
         
-        // Add all MCP tools dynamically
+        // Add all MCP tools dynamically, recording each call in `log`
         let builder = tools.tools
             .into_iter()
             .fold(builder, |builder, tool| {
-                builder.mcp_tool(tool, mcp_client.inner.clone().into())
+                builder.tool(RecordedTool {
+                    mode: (!is_read_only(&tool)).then_some(mode),
+                    inner: McpTool::from_mcp_server(tool, mcp_client.inner.clone()),
+                    log: log.clone(),
+                    profile: profile.to_string(),
+                    project_id: project_id.map(str::to_string),
+                })
             });
 
         builder.build()
     }
 
-    fn get_agent(cfg: &AgentConfig) -> Agent<openai::CompletionModel>{
-        let model = openai::Client::from_url(&cfg.api_key, &cfg.base_url)
-            .completion_model(&cfg.model_name);
-        AgentBuilder::new(model)
-            .preamble(&cfg.prompt)
+    /// Answer `prompt`, running the tools the model asks for until it replies with text.
+    /// With `echo`, the reply is printed as it streams and each tool call is shown as it runs.
+    pub async fn stream_chat(agent: &LlmAgent, prompt: &str, mut history: Vec<Message>, echo: bool) -> Result<String> {
+        let mut prompt = Message::user(prompt);
+        let mut printed_header = false;
+        
+        for _ in 0..MAX_TOOL_ROUNDS {
+            let mut stream = agent.stream_chat(prompt.clone(), history.clone()).await?;
+            let mut text = String::new();
+            let mut tool_calls = Vec::new();
+            
+            while let Some(content) = stream.next().await {
+                match content? {
+                    AssistantContent::Text(chunk) => {
+                        if echo {
+                            if !printed_header {
+                                println!("\n📝 Response:");
+                                printed_header = true;
+                            }
+                            print!("{}", chunk.text);
+                            let _ = std::io::stdout().flush();
+                        }
+                        text.push_str(&chunk.text);
+                    }
+                    AssistantContent::ToolCall(call) => tool_calls.push(call),
+                }
+            }
+            if echo && !text.is_empty() && !text.ends_with('\n') {
+                println!();
+            }
+            
+            history.push(prompt);
+            history.push(Message::Assistant { content: stream.choice.clone() });
+            if tool_calls.is_empty() {
+                return Ok(text);
+            }
+            
+            // Failed calls are reported back to the model so it can try something else
+            let mut results = Vec::with_capacity(tool_calls.len());
+            for call in tool_calls {
+                let output = Self::run_tool(agent, &call, echo).await;
+                results.push(UserContent::tool_result(call.id, OneOrMany::one(ToolResultContent::text(output))));
+            }
+            prompt = Message::User {
+                content: OneOrMany::many(results)?,
+            };
+        }
+        
+        anyhow::bail!("Gave up after {} rounds of tool calls without an answer", MAX_TOOL_ROUNDS)
+    }
+    
+    async fn run_tool(agent: &LlmAgent, call: &rig::message::ToolCall, echo: bool) -> String {
+        let name = &call.function.name;
+        let arguments = call.function.arguments.to_string();
+        if echo {
+            let preview: String = arguments.chars().take(TOOL_ARGS_PREVIEW).collect();
+            let ellipsis = if preview.len() < arguments.len() { "…" } else { "" };
+            print!("  🔧 {} {}{} ", name, preview, ellipsis);
+            let _ = std::io::stdout().flush();
+        }
+        
+        let started = Instant::now();
+        let result = agent.tools.call(name, arguments).await;
+        let elapsed = started.elapsed().as_secs_f64();
+        match result {
+            Ok(output) => {
+                if echo {
+                    println!("✅ {:.1}s", elapsed);
+                }
+                output
+            }
+            Err(e) => {
+                let message = root_cause(&e);
+                if echo {
+                    println!("❌ {:.1}s: {}", elapsed, message);
+                }
+                format!("Error: {}", message)
+            }
+        }
+    }
+
+    /// Start an agent for the configured provider with ken's sampling settings
+    fn builder(cfg: &AgentConfig, preamble: &str) -> BoxAgentBuilder<'static> {
+        cfg.provider.client(&cfg.api_key, &cfg.base_url)
+            .agent(&cfg.model_name)
+            .preamble(preamble)
             .temperature(cfg.temperature)
             .max_tokens(cfg.max_tokens)
-            .build()
     }
 }
+
+/// Wraps a tool so that every call is appended to a `ToolLog` and the audit log
+struct RecordedTool<T> {
+    inner: T,
+    log: ToolLog,
+    /// How calls are gated; `None` for read-only tools, which always run
+    mode: Option<ToolMode>,
+    profile: String,
+    project_id: Option<String>,
+}
+
+impl<T: ToolDyn> RecordedTool<T> {
+    fn record(&self, args: &serde_json::Value, output: String, outcome: Outcome) {
+        let failed = matches!(outcome, Outcome::Failed(_) | Outcome::Declined);
+        let dry_run = matches!(outcome, Outcome::DryRun);
+        audit::record(&self.profile, self.project_id.as_deref(), Action {
+            source: Source::Mcp,
+            tool: &self.inner.name(),
+            arguments: args.clone(),
+            outcome,
+        });
+        if let Ok(mut log) = self.log.lock() {
+            log.push(ToolCall { name: self.inner.name(), arguments: args.to_string(), output, failed, dry_run });
+        }
+    }
+}
+
+impl<T: ToolDyn> Tool for RecordedTool<T> {
+    const NAME: &'static str = "recorded_tool";
+    type Error = ToolError;
+    type Args = serde_json::Value;
+    type Output = serde_json::Value;
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.inner.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match self.mode {
+            Some(ToolMode::DryRun) => {
+                self.record(&args, "Not run (dry run)".to_string(), Outcome::DryRun);
+                return Ok(serde_json::Value::String(format!(
+                    "Dry run: {} was not called and nothing was changed. Tell the user what this call would have done.",
+                    self.inner.name()
+                )));
+            }
+            Some(ToolMode::Confirm) if !approve(&self.inner.name(), &args) => {
+                self.record(&args, "Declined by the user".to_string(), Outcome::Declined);
+                return Err(ToolError::ToolCallError("Declined by the user".into()));
+            }
+            _ => {}
+        }
+
+        let result = self.inner.call(args.to_string()).await;
+        let (output, outcome) = match &result {
+            Ok(output) => (output.chars().take(TOOL_OUTPUT_PREVIEW).collect(), Outcome::Ok),
+            Err(e) => (root_cause(e), Outcome::Failed(root_cause(e))),
+        };
+        self.record(&args, output, outcome);
+
+        // Tool outputs are re-serialized by rig, so pass JSON through as JSON rather than as a quoted string
+        let output = result?;
+        Ok(serde_json::from_str(&output).unwrap_or(serde_json::Value::String(output)))
+    }
+}
+
+/// Show a mutating call with its exact arguments and ask whether to run it.
+/// Without a terminal to ask on, the call is declined.
+fn approve(name: &str, args: &serde_json::Value) -> bool {
+    if !std::io::stdin().is_terminal() {
+        println!("\n🚫 {} changes GitLab data and there is no terminal to approve it on; declined.", name);
+        return false;
+    }
+    let arguments = serde_json::to_string_pretty(args).unwrap_or_else(|_| args.to_string());
+    println!("\n⚠️  {} changes GitLab data. Arguments:\n{}", name, arguments);
+    // The agent runs on the session's task, so block it while the user answers
+    let answer = tokio::task::block_in_place(|| {
+        rustyline::DefaultEditor::new().and_then(|mut editor| editor.readline("Run it? [y/N]: "))
+    });
+    answer.is_ok_and(|answer| matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Message of the innermost error; rig wraps tool errors several times over
+fn root_cause(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut error = error;
+    while let Some(source) = error.source() {
+        error = source;
+    }
+    error.to_string()
+}
@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+use crate::context::ProjectContext;
+use crate::gitlab_client::GitLabIssue;
+use crate::similarity::{document, Bm25Index};
+
+/// Closed issues looked at for ownership patterns, most recently updated first
+pub const HISTORY_SIZE: u32 = 100;
+/// Candidates shown by `/suggest`
+pub const MAX_CANDIDATES: usize = 5;
+
+/// Share of the confidence score each signal contributes
+const SIMILAR_WEIGHT: f64 = 0.45;
+const LABEL_WEIGHT: f64 = 0.25;
+const LOAD_WEIGHT: f64 = 0.2;
+const ROLE_WEIGHT: f64 = 0.1;
+/// Summed BM25 similarity of resolved issues at which that signal is maxed out
+const SIMILAR_SATURATION: f64 = 1.5;
+/// Issues with a shared label at which label affinity is maxed out
+const LABEL_SATURATION: f64 = 5.0;
+/// Workload score (issues + 2 × MRs) that halves the load signal
+const LOAD_HALF: f64 = 3.0;
+/// Past issues less similar than this are not counted as similar
+const MIN_SIMILARITY: f64 = 0.2;
+
+/// A project member ranked for an issue, with the evidence behind the score
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub username: String,
+    /// 0.0 to 1.0
+    pub confidence: f64,
+    /// IIDs of similar closed issues this member resolved, most similar first
+    pub similar_issues: Vec<u64>,
+    /// Issues sharing a label with this one that this member worked on
+    pub label_matches: usize,
+    pub matched_labels: Vec<String>,
+    pub open_issues: usize,
+    pub open_mrs: usize,
+    pub role: Option<String>,
+    /// Already assigned to the issue
+    pub assigned: bool,
+}
+
+impl Candidate {
+    /// One-line explanation of the score
+    pub fn rationale(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.similar_issues.is_empty() {
+            let refs: Vec<String> = self.similar_issues.iter().take(3).map(|iid| format!("#{}", iid)).collect();
+            parts.push(format!("resolved {} similar issue{} ({})",
+                self.similar_issues.len(), plural(self.similar_issues.len()), refs.join(", ")));
+        }
+        if self.label_matches > 0 {
+            parts.push(format!("worked on {} issue{} labelled {}",
+                self.label_matches, plural(self.label_matches), self.matched_labels.join(", ")));
+        }
+        if self.open_issues == 0 && self.open_mrs == 0 {
+            parts.push("no open work".to_string());
+        } else {
+            parts.push(format!("{} open issue{}, {} open MR{}",
+                self.open_issues, plural(self.open_issues), self.open_mrs, plural(self.open_mrs)));
+        }
+        if let Some(role) = &self.role {
+            parts.push(role.clone());
+        }
+        if self.assigned {
+            parts.push("already assigned".to_string());
+        }
+        parts.join("; ")
+    }
+
+    /// Whether the score rests on anything besides workload and role
+    pub fn has_history(&self) -> bool {
+        !self.similar_issues.is_empty() || self.label_matches > 0
+    }
+}
+
+/// Rank project members for `issue` by who resolved similar closed issues, who works
+/// on issues with the same labels, how much open work they have and their role.
+/// Similarity is BM25 over title and description only; shared labels are scored separately
+/// as label affinity, so one shared label doesn't count twice.
+pub fn rank(issue: &GitLabIssue, closed: &[GitLabIssue], context: &ProjectContext) -> Vec<Candidate> {
+    let labels: HashSet<&str> = issue.labels.iter().map(String::as_str).collect();
+    let assigned = issue.assignee_usernames();
+
+    let past: Vec<&GitLabIssue> = closed.iter().filter(|past| past.iid != issue.iid).collect();
+    let index = Bm25Index::new(past.iter().map(|past| (past.iid, document(&past.title, past.description.as_deref()))));
+    let similarities: HashMap<u64, f64> = index.search(&document(&issue.title, issue.description.as_deref())).into_iter().collect();
+
+    let mut candidates: Vec<Candidate> = context.users.iter()
+        .map(|user| {
+            let username = user.username.as_str();
+
+            let mut similar: Vec<(u64, f64)> = past.iter()
+                .filter(|past| resolved_by(past, username))
+                .filter_map(|past| similarities.get(&past.iid).map(|similarity| (past.iid, *similarity)))
+                .filter(|(_, similarity)| *similarity >= MIN_SIMILARITY)
+                .collect();
+            similar.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let similar_score: f64 = similar.iter().map(|(_, similarity)| similarity).sum();
+
+            // Open issues from the cached context count as well as closed ones
+            let mut matched_labels: Vec<String> = Vec::new();
+            let mut label_matches = 0;
+            let worked_on = past.iter()
+                .filter(|past| resolved_by(past, username))
+                .map(|past| &past.labels)
+                .chain(context.hot_issues.iter()
+                    .filter(|open| open.id as u64 != issue.iid && open.assignee.as_deref() == Some(username))
+                    .map(|open| &open.labels));
+            for issue_labels in worked_on {
+                let shared: Vec<&String> = issue_labels.iter().filter(|l| labels.contains(l.as_str())).collect();
+                if shared.is_empty() {
+                    continue;
+                }
+                label_matches += 1;
+                for label in shared {
+                    if !matched_labels.contains(label) {
+                        matched_labels.push(label.clone());
+                    }
+                }
+            }
+
+            let workload = context.workload_data.user_assignments.get(username);
+            let load = workload.map_or(0, |w| w.total_score) as f64;
+
+            let confidence = SIMILAR_WEIGHT * (similar_score / SIMILAR_SATURATION).min(1.0)
+                + LABEL_WEIGHT * (label_matches as f64 / LABEL_SATURATION).min(1.0)
+                + LOAD_WEIGHT * (1.0 / (1.0 + load / LOAD_HALF))
+                + ROLE_WEIGHT * role_fit(user.role.as_deref());
+
+            Candidate {
+                username: user.username.clone(),
+                confidence,
+                similar_issues: similar.into_iter().map(|(iid, _)| iid).collect(),
+                label_matches,
+                matched_labels,
+                open_issues: workload.map_or(0, |w| w.issue_count),
+                open_mrs: workload.map_or(0, |w| w.mr_count),
+                role: user.role.clone(),
+                assigned: assigned.contains(&username),
+            }
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then_with(|| a.username.cmp(&b.username)));
+    candidates
+}
+
+/// Whether `username` closed the issue or was assigned to it when it was closed
+fn resolved_by(issue: &GitLabIssue, username: &str) -> bool {
+    issue.closed_by.as_ref().is_some_and(|user| user.username == username)
+        || issue.assignee_usernames().contains(&username)
+}
+
+/// How well a role fits taking on work: developers and maintainers first
+fn role_fit(role: Option<&str>) -> f64 {
+    match role {
+        Some("Developer") | Some("Maintainer") => 1.0,
+        Some("Owner") => 0.8,
+        Some("Reporter") => 0.3,
+        Some("Guest") => 0.1,
+        _ => 0.5,
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 { "" } else { "s" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::ProjectUser;
+
+    fn issue(iid: u64, title: &str, labels: &[&str], closed_by: Option<&str>) -> GitLabIssue {
+        serde_json::from_value(serde_json::json!({
+            "id": iid, "iid": iid, "title": title, "state": if closed_by.is_some() { "closed" } else { "opened" },
+            "labels": labels, "closed_by": closed_by.map(|username| serde_json::json!({ "id": 1, "username": username })),
+        })).unwrap()
+    }
+
+    #[test]
+    fn shared_labels_alone_are_not_similarity() {
+        let context = ProjectContext {
+            users: ["alice", "bob"].iter()
+                .map(|name| ProjectUser { username: name.to_string(), name: None, email: None, role: None })
+                .collect(),
+            ..ProjectContext::default()
+        };
+        let new = issue(10, "Upload crashes on large files", &["backend"], None);
+        let closed = [
+            issue(1, "Dark mode colours are off", &["backend"], Some("alice")),
+            issue(2, "Crash when uploading large files", &["frontend"], Some("bob")),
+        ];
+
+        let candidates = rank(&new, &closed, &context);
+        let alice = candidates.iter().find(|c| c.username == "alice").unwrap();
+        let bob = candidates.iter().find(|c| c.username == "bob").unwrap();
+        assert!(alice.similar_issues.is_empty());
+        assert_eq!((alice.label_matches, alice.matched_labels.as_slice()), (1, ["backend".to_string()].as_slice()));
+        assert_eq!(bob.similar_issues, vec![2]);
+        assert_eq!(bob.label_matches, 0);
+    }
+}
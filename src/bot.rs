@@ -0,0 +1,672 @@
+use anyhow::{Context, Result};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use crate::activity::{self, DeveloperActivity};
+use crate::agent::{strip_reasoning, AgentConfig, KenAgent, LlmAgent};
+use crate::assignee;
+use crate::audit;
+use crate::config::Config;
+use crate::gitlab_client::GitLabClient;
+use crate::gitlab_tools::GitLabTools;
+use crate::context::ProjectContext;
+use crate::issue_summary::IssueThread;
+use rig::completion::Prompt;
+
+/// Environment variable holding the secret token configured on the GitLab webhook
+pub const SECRET_ENV: &str = "KEN_WEBHOOK_SECRET";
+/// Address `ken bot` listens on unless `--listen` is given
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+/// Header GitLab sends the webhook's secret token in
+const TOKEN_HEADER: &str = "X-Gitlab-Token";
+const EVENT_HEADER: &str = "X-Gitlab-Event";
+/// Note events are small; anything bigger is not from GitLab
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Mentions waiting to be answered. Further webhooks are turned away until the queue drains.
+const QUEUE_SIZE: usize = 32;
+
+/// What a comment mentioning the bot asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotCommand {
+    /// `@ken summarize`
+    Summarize,
+    /// `@ken suggest`
+    Suggest,
+    /// `@ken what is @charlie working on?`
+    WorkingOn(String),
+    /// A mention that matches no command; answered with the list of commands
+    Help,
+}
+
+impl BotCommand {
+    /// The command addressed to `@handle` in a comment, if any.
+    /// Quoted lines and code blocks are skipped, so quoting an earlier request doesn't run it again.
+    pub fn parse(note: &str, handle: &str) -> Option<Self> {
+        let mention = format!("@{}", handle.to_lowercase());
+        let mut in_code = false;
+        for line in note.lines().map(str::trim) {
+            if line.starts_with("```") {
+                in_code = !in_code;
+                continue;
+            }
+            if in_code || line.starts_with('>') {
+                continue;
+            }
+            if let Some(request) = after_mention(&line.to_lowercase(), &mention) {
+                return Some(Self::from_request(request));
+            }
+        }
+        None
+    }
+
+    fn from_request(request: &str) -> Self {
+        let request = request.trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ',' | ':'));
+        match request.split(|c: char| !c.is_alphanumeric()).next().unwrap_or("") {
+            "summarize" | "summarise" | "summary" => BotCommand::Summarize,
+            "suggest" => BotCommand::Suggest,
+            _ if request.contains("working on") => match mentions(request).next() {
+                Some(username) => BotCommand::WorkingOn(username.to_string()),
+                None => BotCommand::Help,
+            },
+            _ => BotCommand::Help,
+        }
+    }
+}
+
+/// The text after the first `mention` that is a whole username, not part of a longer one or an email address
+fn after_mention<'a>(line: &'a str, mention: &str) -> Option<&'a str> {
+    line.match_indices(mention).find_map(|(start, _)| {
+        let rest = &line[start + mention.len()..];
+        let mut after = rest.chars();
+        let longer = match after.next() {
+            Some(c) if is_username_char(c) && c != '.' => true,
+            Some('.') => after.next().is_some_and(char::is_alphanumeric),
+            _ => false,
+        };
+        let embedded = line[..start].chars().next_back().is_some_and(char::is_alphanumeric);
+        (!longer && !embedded).then_some(rest)
+    })
+}
+
+/// Usernames mentioned in `text`, without the '@'
+fn mentions(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .filter_map(|word| word.strip_prefix('@'))
+        .map(|name| name.trim_end_matches(|c: char| !is_username_char(c) || c == '.'))
+        .filter(|name| !name.is_empty())
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Wrap `@username` mentions in code spans, so a reply doesn't notify everyone it names
+fn quiet_mentions(text: &str) -> String {
+    let mut quiet = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        let (before, after) = rest.split_at(at);
+        quiet.push_str(before);
+        let name = &after[1..];
+        let name = &name[..name.find(|c: char| !is_username_char(c)).unwrap_or(name.len())];
+        let name = name.trim_end_matches('.');
+        let quoted = before.chars().next_back().is_some_and(|c| c.is_alphanumeric() || c == '`');
+        if name.is_empty() || quoted {
+            quiet.push('@');
+            rest = &after[1..];
+        } else {
+            quiet.push_str(&format!("`@{}`", name));
+            rest = &after[1 + name.len()..];
+        }
+    }
+    quiet.push_str(rest);
+    quiet
+}
+
+/// The parts of a GitLab "Note Hook" payload the bot uses
+#[derive(Debug, Deserialize)]
+pub struct NoteEvent {
+    pub object_kind: String,
+    pub user: EventUser,
+    pub project: EventProject,
+    pub object_attributes: NoteAttributes,
+    pub issue: Option<EventNoteable>,
+    pub merge_request: Option<EventNoteable>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventUser {
+    pub username: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventProject {
+    pub id: u64,
+    #[serde(default)]
+    pub path_with_namespace: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NoteAttributes {
+    pub note: String,
+    pub noteable_type: String,
+    pub discussion_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventNoteable {
+    pub iid: u64,
+}
+
+/// A mention waiting to be answered
+struct Job {
+    project_id: String,
+    /// API path segment of the commented item, `issues` or `merge_requests`
+    noteable: &'static str,
+    iid: u64,
+    discussion_id: String,
+    /// e.g. "group/project#12", for the log
+    reference: String,
+    author: String,
+    /// The comment itself, recorded in the audit log as the request
+    note: String,
+    command: BotCommand,
+}
+
+impl Job {
+    /// The job for a note event, or None if the note doesn't ask the bot anything
+    fn from_event(event: NoteEvent, handle: &str) -> Option<Self> {
+        // The bot's own replies may quote a request; never answer them
+        if event.object_kind != "note" || event.user.username.eq_ignore_ascii_case(handle) {
+            return None;
+        }
+        let command = BotCommand::parse(&event.object_attributes.note, handle)?;
+        let (noteable, sigil, iid) = match event.object_attributes.noteable_type.as_str() {
+            "Issue" => ("issues", '#', event.issue?.iid),
+            "MergeRequest" => ("merge_requests", '!', event.merge_request?.iid),
+            _ => return None,
+        };
+        Some(Self {
+            project_id: event.project.id.to_string(),
+            noteable,
+            iid,
+            discussion_id: event.object_attributes.discussion_id?,
+            reference: format!("{}{}{}", event.project.path_with_namespace, sigil, iid),
+            author: event.user.username,
+            note: event.object_attributes.note,
+            command,
+        })
+    }
+}
+
+/// Checks incoming webhooks and queues the mentions in them
+struct Receiver {
+    secret: String,
+    handle: String,
+    jobs: mpsc::Sender<Job>,
+}
+
+impl Receiver {
+    async fn accept(&self, request: Request<Incoming>) -> (StatusCode, &'static str) {
+        if request.method() != Method::POST {
+            return (StatusCode::METHOD_NOT_ALLOWED, "Only POST is supported");
+        }
+        let token = request.headers().get(TOKEN_HEADER).map(|value| value.as_bytes()).unwrap_or_default();
+        if !token_matches(token, self.secret.as_bytes()) {
+            println!("🚫 Rejected a webhook with a missing or wrong secret token");
+            return (StatusCode::UNAUTHORIZED, "Invalid token");
+        }
+        if request.headers().get(EVENT_HEADER).is_none_or(|event| event != "Note Hook") {
+            return (StatusCode::OK, "Ignored: not a note event");
+        }
+
+        let body = match Limited::new(request.into_body(), MAX_BODY_BYTES).collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(_) => return (StatusCode::BAD_REQUEST, "Could not read the request body"),
+        };
+        let event: NoteEvent = match serde_json::from_slice(&body) {
+            Ok(event) => event,
+            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid note event"),
+        };
+        let Some(job) = Job::from_event(event, &self.handle) else {
+            return (StatusCode::OK, "Ignored: no request for the bot");
+        };
+
+        println!("📨 @{} on {}: {:?}", job.author, job.reference, job.command);
+        match self.jobs.try_send(job) {
+            Ok(()) => (StatusCode::ACCEPTED, "Queued"),
+            Err(_) => {
+                println!("⚠️  Queue full; turned the request away");
+                (StatusCode::SERVICE_UNAVAILABLE, "Too many pending requests")
+            }
+        }
+    }
+}
+
+/// Compare in constant time, so response times don't reveal how much of a guessed token was right
+fn token_matches(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given.iter().zip(expected).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Answers queued mentions one at a time and posts the replies
+struct Worker {
+    config: Config,
+    client: GitLabClient,
+    agent: LlmAgent,
+    agent_config: AgentConfig,
+    handle: String,
+}
+
+impl Worker {
+    async fn run(self, mut queue: mpsc::Receiver<Job>) {
+        while let Some(job) = queue.recv().await {
+            audit::begin(&job.note, Some(&format!("@{}", job.author)));
+            let reply = match self.answer(&job).await {
+                Ok(reply) => reply,
+                Err(e) => {
+                    println!("❌ {}: {}", job.reference, e);
+                    format!("Sorry, I couldn't do that: {}", e)
+                }
+            };
+            match self.client.reply_to_discussion(&job.project_id, job.noteable, job.iid, &job.discussion_id, &reply).await {
+                Ok(_) => println!("✅ Replied on {}", job.reference),
+                Err(e) => println!("❌ Failed to reply on {}: {}", job.reference, e),
+            }
+        }
+    }
+
+    async fn answer(&self, job: &Job) -> Result<String> {
+        match &job.command {
+            BotCommand::Summarize | BotCommand::Suggest if job.noteable != "issues" => {
+                anyhow::bail!("summarize and suggest only work on issues")
+            }
+            BotCommand::Summarize => self.summarize(job).await,
+            BotCommand::Suggest => self.suggest(job).await,
+            BotCommand::WorkingOn(username) => self.working_on(job, username).await,
+            BotCommand::Help => Ok(self.help()),
+        }
+    }
+
+    async fn summarize(&self, job: &Job) -> Result<String> {
+        let (issue, discussions) = tokio::try_join!(
+            self.client.get_issue(&job.project_id, job.iid),
+            self.client.list_issue_discussions(&job.project_id, job.iid),
+        )?;
+        let thread = IssueThread { issue, discussions };
+        let summary = thread.summarize(&self.agent, self.agent_config.history_budget()).await?;
+        if summary.is_empty() {
+            anyhow::bail!("the model returned an empty summary");
+        }
+        Ok(thread.comment(&quiet_mentions(&summary)))
+    }
+
+    /// Ranked candidates only; assigning is left to a person
+    async fn suggest(&self, job: &Job) -> Result<String> {
+        let context = ProjectContext::cached(&self.config, &job.project_id).await?;
+        let (issue, closed) = tokio::try_join!(
+            self.client.get_issue(&job.project_id, job.iid),
+            self.client.list_recent_issues(
+                &job.project_id,
+                &[("state", "closed"), ("order_by", "updated_at")],
+                assignee::HISTORY_SIZE,
+            ),
+        )?;
+
+        let candidates = assignee::rank(&issue, &closed, &context);
+        let Some(top) = candidates.first() else {
+            anyhow::bail!("no project members are known yet");
+        };
+        let mut reply = String::from("**Suggested assignees**\n\n");
+        for (i, candidate) in candidates.iter().take(assignee::MAX_CANDIDATES).enumerate() {
+            reply.push_str(&format!("{}. `@{}` {:.0}%: {}\n",
+                i + 1, candidate.username, candidate.confidence * 100.0, candidate.rationale()));
+        }
+        if !top.has_history() {
+            reply.push_str("\nNo similar closed issues or shared labels found; the ranking rests on workload and role only.\n");
+        }
+        Ok(reply)
+    }
+
+    async fn working_on(&self, job: &Job, username: &str) -> Result<String> {
+        let mut config = self.config.clone();
+        config.default_project_id = Some(job.project_id.clone());
+        let activity = DeveloperActivity::collect(&GitLabTools::new(config), username, activity::DEFAULT_DAYS).await?;
+        let summary = self.agent.prompt(activity.summary_prompt().as_str()).await?;
+        Ok(format!(
+            "{}\n\n<details><summary>Data for the last {} days</summary>\n\n```text\n{}\n```\n\n</details>",
+            quiet_mentions(strip_reasoning(&summary)),
+            activity.days,
+            activity.report().trim_end()
+        ))
+    }
+
+    fn help(&self) -> String {
+        format!(
+            "I can help with:\n\
+             - `@{handle} summarize`: summarize this issue and its discussion\n\
+             - `@{handle} suggest`: suggest who should take this issue\n\
+             - `@{handle} what is @username working on?`: summarize someone's recent work",
+            handle = self.handle
+        )
+    }
+}
+
+/// Listen for GitLab note webhooks on `addr` and answer mentions of the token's user until Ctrl-C.
+/// Any project whose webhook carries the secret token is served, using that project's data.
+pub async fn serve(config: Config, addr: SocketAddr) -> Result<()> {
+    let secret = std::env::var(SECRET_ENV).ok()
+        .filter(|secret| !secret.trim().is_empty())
+        .with_context(|| format!("Set {} to the secret token configured on the GitLab webhook", SECRET_ENV))?;
+    let client = GitLabClient::new(&config);
+    let handle = client.current_user().await.context("Failed to look up the bot's GitLab user")?.username;
+    let agent_config = AgentConfig::from_llm(&config.llm)?;
+    let agent = KenAgent::build(&agent_config);
+
+    let (jobs, queue) = mpsc::channel(QUEUE_SIZE);
+    let worker = Worker { config, client, agent, agent_config, handle: handle.clone() };
+    tokio::spawn(worker.run(queue));
+    let receiver = Arc::new(Receiver { secret, handle: handle.clone(), jobs });
+
+    let listener = TcpListener::bind(addr).await.with_context(|| format!("Failed to listen on {}", addr))?;
+    println!("🤖 Answering @{} mentions from note webhooks on http://{}/ (Ctrl-C to stop)", handle, addr);
+    tokio::select! {
+        _ = listen(listener, receiver) => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    println!("👋 Bot stopped");
+    Ok(())
+}
+
+/// Hand each webhook arriving on `listener` to `receiver`
+async fn listen(listener: TcpListener, receiver: Arc<Receiver>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                println!("⚠️  Failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+        let receiver = receiver.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let receiver = receiver.clone();
+                async move {
+                    let (status, message) = receiver.accept(request).await;
+                    let mut response = Response::new(Full::new(Bytes::from(message)));
+                    *response.status_mut() = status;
+                    Ok::<_, Infallible>(response)
+                }
+            });
+            if let Err(e) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                tracing::debug!("Webhook connection error: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LlmConfig, ToolsConfig};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(BotCommand::parse("@ken summarize", "ken"), Some(BotCommand::Summarize));
+        assert_eq!(BotCommand::parse("Hey @Ken, please summarise this", "ken"), Some(BotCommand::Help));
+        assert_eq!(BotCommand::parse("@ken: summary please", "ken"), Some(BotCommand::Summarize));
+        assert_eq!(BotCommand::parse("thanks!\n@ken suggest", "ken"), Some(BotCommand::Suggest));
+        assert_eq!(
+            BotCommand::parse("@ken what is @Charlie.Dev working on?", "ken"),
+            Some(BotCommand::WorkingOn("charlie.dev".to_string()))
+        );
+        assert_eq!(BotCommand::parse("@ken what are you working on?", "ken"), Some(BotCommand::Help));
+        assert_eq!(BotCommand::parse("@ken hello", "ken"), Some(BotCommand::Help));
+        assert_eq!(BotCommand::parse("no mention here", "ken"), None);
+    }
+
+    #[test]
+    fn skips_quotes_and_code() {
+        assert_eq!(BotCommand::parse("> @ken summarize\nthanks", "ken"), None);
+        assert_eq!(BotCommand::parse("```\n@ken suggest\n```", "ken"), None);
+        assert_eq!(BotCommand::parse("```\n@ken suggest\n```\n@ken summarize", "ken"), Some(BotCommand::Summarize));
+    }
+
+    #[test]
+    fn mention_must_be_whole_username() {
+        assert_eq!(after_mention("@ken summarize", "@ken"), Some(" summarize"));
+        assert_eq!(after_mention("@kenneth summarize", "@ken"), None);
+        assert_eq!(after_mention("@ken-bot summarize", "@ken"), None);
+        assert_eq!(after_mention("@ken.bot summarize", "@ken"), None);
+        assert_eq!(after_mention("mail me@ken summarize", "@ken"), None);
+        assert_eq!(after_mention("thanks @ken.", "@ken"), Some("."));
+        assert_eq!(after_mention("@kenneth and @ken suggest", "@ken"), Some(" suggest"));
+    }
+
+    #[test]
+    fn quiets_mentions() {
+        assert_eq!(quiet_mentions("ask @alice or @bob-2."), "ask `@alice` or `@bob-2`.");
+        assert_eq!(quiet_mentions("mail a@example.com"), "mail a@example.com");
+        assert_eq!(quiet_mentions("already `@carol`"), "already `@carol`");
+        assert_eq!(quiet_mentions("a lone @ sign"), "a lone @ sign");
+    }
+
+    fn event(note: &str, author: &str, noteable_type: &str, iid: u64) -> NoteEvent {
+        let noteable = serde_json::json!({ "iid": iid });
+        serde_json::from_value(serde_json::json!({
+            "object_kind": "note",
+            "user": { "username": author },
+            "project": { "id": 1, "path_with_namespace": "group/project" },
+            "object_attributes": { "note": note, "noteable_type": noteable_type, "discussion_id": "d1" },
+            "issue": if noteable_type == "Issue" { noteable.clone() } else { serde_json::Value::Null },
+            "merge_request": if noteable_type == "MergeRequest" { noteable } else { serde_json::Value::Null },
+        })).unwrap()
+    }
+
+    #[test]
+    fn builds_jobs_from_events() {
+        let job = Job::from_event(event("@ken summarize", "alice", "Issue", 7), "ken").unwrap();
+        assert_eq!((job.project_id.as_str(), job.noteable, job.iid), ("1", "issues", 7));
+        assert_eq!((job.discussion_id.as_str(), job.reference.as_str()), ("d1", "group/project#7"));
+        assert_eq!((job.author.as_str(), job.command), ("alice", BotCommand::Summarize));
+
+        let job = Job::from_event(event("@ken suggest", "alice", "MergeRequest", 3), "ken").unwrap();
+        assert_eq!((job.noteable, job.reference.as_str()), ("merge_requests", "group/project!3"));
+
+        assert!(Job::from_event(event("@ken summarize", "KEN", "Issue", 7), "ken").is_none());
+        assert!(Job::from_event(event("nothing for the bot", "alice", "Issue", 7), "ken").is_none());
+        assert!(Job::from_event(event("@ken summarize", "alice", "Snippet", 7), "ken").is_none());
+        let mut no_discussion = event("@ken summarize", "alice", "Issue", 7);
+        no_discussion.object_attributes.discussion_id = None;
+        assert!(Job::from_event(no_discussion, "ken").is_none());
+    }
+
+    #[test]
+    fn compares_tokens() {
+        assert!(token_matches(b"s3cret", b"s3cret"));
+        assert!(!token_matches(b"s3creT", b"s3cret"));
+        assert!(!token_matches(b"s3cre", b"s3cret"));
+        assert!(!token_matches(b"", b"s3cret"));
+    }
+
+    /// Method, path and body of a request the stand-in server received
+    type Calls = Arc<Mutex<Vec<(String, String, String)>>>;
+
+    /// Serve canned GitLab API and OpenAI-style chat responses on a local port, recording every request
+    async fn stand_in() -> (String, Calls) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let calls: Calls = Arc::default();
+        let recorded = calls.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let calls = recorded.clone();
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service_fn(move |request: Request<Incoming>| {
+                    let calls = calls.clone();
+                    async move {
+                        let method = request.method().to_string();
+                        let path = request.uri().path().to_string();
+                        let body = String::from_utf8(request.into_body().collect().await.unwrap().to_bytes().to_vec()).unwrap();
+                        calls.lock().unwrap().push((method.clone(), path.clone(), body));
+                        let reply = match (method.as_str(), path.as_str()) {
+                            ("GET", "/api/v4/projects/1/issues/7") => serde_json::json!({
+                                "id": 70, "iid": 7, "title": "Upload crashes", "state": "opened",
+                                "description": "Uploads over 100MB crash.", "author": { "id": 2, "username": "bob" },
+                            }),
+                            ("GET", "/api/v4/projects/1/issues/7/discussions") => serde_json::json!([{
+                                "id": "d1", "notes": [{ "id": 1, "body": "Fixed in !4", "author": { "id": 3, "username": "alice" } }],
+                            }]),
+                            ("POST", path) if path.ends_with("/notes") => serde_json::json!({ "id": 99, "body": "" }),
+                            ("POST", path) if path.ends_with("/chat/completions") => {
+                                // Summaries stream their final pass
+                                let chunk = serde_json::json!({
+                                    "id": "c1", "object": "chat.completion.chunk", "created": 0, "model": "test",
+                                    "choices": [{ "index": 0, "finish_reason": "stop",
+                                        "delta": { "role": "assistant", "content": "The crash was fixed by @alice in !4." } }],
+                                });
+                                let stream = format!("data: {}\n\ndata: [DONE]\n\n", chunk);
+                                let mut response = Response::new(Full::new(Bytes::from(stream)));
+                                response.headers_mut().insert("content-type", "text/event-stream".parse().unwrap());
+                                return Ok(response);
+                            }
+                            _ => {
+                                let mut response = Response::new(Full::new(Bytes::from("{}")));
+                                *response.status_mut() = StatusCode::NOT_FOUND;
+                                return Ok::<_, Infallible>(response);
+                            }
+                        };
+                        let mut response = Response::new(Full::new(Bytes::from(reply.to_string())));
+                        response.headers_mut().insert("content-type", "application/json".parse().unwrap());
+                        Ok(response)
+                    }
+                })));
+            }
+        });
+        (url, calls)
+    }
+
+    /// Start the webhook receiver and worker against `server`, returning the webhook URL
+    async fn start_bot(server: &str) -> String {
+        let config = Config {
+            profile: "test".to_string(),
+            gitlab_url: server.to_string(),
+            api_token: "token".to_string(),
+            default_project_id: None,
+            max_pages: None,
+            max_retries: Some(0),
+            concurrency: None,
+            llm: LlmConfig {
+                base_url: Some(format!("{}/v1", server)),
+                api_key: Some("key".to_string()),
+                model: Some("test".to_string()),
+                ..LlmConfig::default()
+            },
+            tools: ToolsConfig::default(),
+            dry_run: false,
+        };
+        let agent_config = AgentConfig::from_llm(&config.llm).unwrap();
+        let agent = KenAgent::build(&agent_config);
+        let client = GitLabClient::new(&config);
+        let (jobs, queue) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(Worker { config, client, agent, agent_config, handle: "ken".to_string() }.run(queue));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let receiver = Arc::new(Receiver { secret: "s3cret".to_string(), handle: "ken".to_string(), jobs });
+        tokio::spawn(listen(listener, receiver));
+        url
+    }
+
+    async fn replay(hook: &str, token: &str, event: &str, payload: serde_json::Value) -> u16 {
+        reqwest::Client::new().post(hook)
+            .header(TOKEN_HEADER, token)
+            .header(EVENT_HEADER, event)
+            .json(&payload)
+            .send().await.unwrap()
+            .status().as_u16()
+    }
+
+    fn payload(note: &str, author: &str, noteable_type: &str, iid: u64, discussion_id: &str) -> serde_json::Value {
+        let mut payload = serde_json::json!({
+            "object_kind": "note",
+            "user": { "id": 9, "username": author },
+            "project": { "id": 1, "path_with_namespace": "group/project" },
+            "object_attributes": { "id": 5, "note": note, "noteable_type": noteable_type, "discussion_id": discussion_id },
+        });
+        let key = if noteable_type == "Issue" { "issue" } else { "merge_request" };
+        payload[key] = serde_json::json!({ "iid": iid, "title": "t" });
+        payload
+    }
+
+    #[tokio::test]
+    async fn answers_replayed_note_hooks() {
+        let home = std::env::temp_dir().join(format!("ken-bot-{}", std::process::id()));
+        audit::keep_under(&home);
+        let (server, calls) = stand_in().await;
+        let hook = start_bot(&server).await;
+        let summarize = payload("@ken summarize", "carol", "Issue", 7, "d1");
+
+        assert_eq!(replay(&hook, "wrong", "Note Hook", summarize.clone()).await, 401);
+        assert_eq!(replay(&hook, "s3cret", "Push Hook", summarize.clone()).await, 200);
+        assert_eq!(replay(&hook, "s3cret", "Note Hook", payload("> @ken summarize", "ken", "Issue", 7, "d1")).await, 200);
+        assert_eq!(replay(&hook, "s3cret", "Note Hook", payload("@ken summarize", "ken", "Issue", 7, "d1")).await, 200);
+        assert_eq!(replay(&hook, "s3cret", "Note Hook", summarize).await, 202);
+        assert_eq!(replay(&hook, "s3cret", "Note Hook", payload("@ken summarize", "carol", "MergeRequest", 3, "d2")).await, 202);
+        assert_eq!(replay(&hook, "s3cret", "Note Hook", payload("hi @ken", "carol", "MergeRequest", 3, "d3")).await, 202);
+
+        let replies = || -> Vec<(String, String)> {
+            calls.lock().unwrap().iter()
+                .filter(|(method, path, _)| method == "POST" && path.ends_with("/notes"))
+                .map(|(_, path, body)| {
+                    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+                    (path.clone(), body["body"].as_str().unwrap().to_string())
+                })
+                .collect()
+        };
+        for _ in 0..100 {
+            if replies().len() >= 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let replies = replies();
+        assert_eq!(replies.len(), 3, "{:?}", replies);
+        assert_eq!(replies[0].0, "/api/v4/projects/1/issues/7/discussions/d1/notes");
+        assert!(replies[0].1.starts_with("**Summary by Ken** of 1 comments"), "{}", replies[0].1);
+        assert!(replies[0].1.contains("fixed by `@alice` in !4"), "{}", replies[0].1);
+        assert_eq!(replies[1].0, "/api/v4/projects/1/merge_requests/3/discussions/d2/notes");
+        assert!(replies[1].1.contains("only work on issues"), "{}", replies[1].1);
+        assert_eq!(replies[2].0, "/api/v4/projects/1/merge_requests/3/discussions/d3/notes");
+        assert!(replies[2].1.starts_with("I can help with"), "{}", replies[2].1);
+
+        let prompts = calls.lock().unwrap().iter().filter(|(_, path, _)| path.ends_with("/chat/completions")).count();
+        assert_eq!(prompts, 1);
+        
+        // Every reply is recorded once GitLab has answered it, in the test's own log
+        let entries = || audit::load(&audit::AuditFilter::default()).unwrap();
+        for _ in 0..100 {
+            if entries().len() >= 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let entries = entries();
+        assert_eq!(entries.len(), 3, "{:?}", entries);
+        assert!(entries.iter().all(|entry| entry.requested_by.as_deref() == Some("@carol")));
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+use std::fmt;
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use crate::activity;
+use crate::audit::{self, AuditFilter};
+use crate::bot;
+use crate::gitlab_client::GitLabError;
+use crate::report::{Period, ReportFormat};
+use crate::interactive::KenSession;
+
+/// Exit code for runtime failures (API errors, LLM errors, ...)
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code when ken is not logged in or has no project configured
+pub const EXIT_NOT_CONFIGURED: u8 = 3;
+
+#[derive(Debug, Parser)]
+#[command(name = "ken", version, about = "AI-powered GitLab assistant")]
+pub struct Cli {
+    /// Config profile (GitLab instance) to use instead of the active one
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Project ID or path to use instead of the configured default
+    #[arg(long, global = true)]
+    pub project: Option<String>,
+
+    /// Record the changes Ken would make to GitLab instead of making them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Draft an issue from a natural language description, review it and create it
+    Issue {
+        /// Plain-text description of the problem or task
+        description: String,
+        /// Create the draft without asking for review
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Rank who should take an issue, with a confidence score and rationale for each
+    Suggest {
+        /// Issue IID within the project
+        iid: u64,
+        /// Assign the top pick without asking
+        #[arg(long)]
+        assign: bool,
+    },
+    /// List likely duplicates of an issue among open and recently closed issues
+    Duplicates {
+        /// Issue IID within the project
+        iid: u64,
+    },
+    /// Summarize an issue and its whole discussion
+    Summarize {
+        /// Issue IID within the project
+        iid: u64,
+        /// Post the summary as a comment on the issue without asking
+        #[arg(long)]
+        post: bool,
+    },
+    /// Summarize a developer's assigned work, review queue, recent activity and blockers
+    Summary {
+        /// GitLab username, with or without a leading '@'
+        user: String,
+        /// Days of activity to cover
+        #[arg(long, default_value_t = activity::DEFAULT_DAYS, value_parser = clap::value_parser!(i64).range(1..))]
+        days: i64,
+    },
+    /// Generate a team progress report
+    Report {
+        /// Cover the last 24 hours
+        #[arg(long, conflicts_with = "weekly")]
+        daily: bool,
+        /// Cover the last 7 days (default)
+        #[arg(long)]
+        weekly: bool,
+        /// Output format: md, text or json
+        #[arg(long, default_value = "md")]
+        format: ReportFormat,
+    },
+    /// Answer @mentions in GitLab comments, received as note webhooks
+    Bot {
+        /// Address to listen on for webhooks
+        #[arg(long, default_value = bot::DEFAULT_LISTEN)]
+        listen: SocketAddr,
+    },
+    /// List agent tool calls and GitLab writes from the audit log (filter by project with --project)
+    Audit {
+        /// Only this day (YYYY-MM-DD)
+        #[arg(long, value_parser = audit::parse_date, conflicts_with_all = ["since", "until"])]
+        date: Option<NaiveDate>,
+        /// From this day on (YYYY-MM-DD)
+        #[arg(long, value_parser = audit::parse_date)]
+        since: Option<NaiveDate>,
+        /// Up to and including this day (YYYY-MM-DD)
+        #[arg(long, value_parser = audit::parse_date)]
+        until: Option<NaiveDate>,
+        /// Tool name or glob, e.g. 'update_*' or 'PUT *'
+        #[arg(long)]
+        tool: Option<String>,
+    },
+    /// Show team workload
+    Workload {
+        /// Query GitLab directly instead of using the cached context
+        #[arg(long)]
+        live: bool,
+    },
+    /// Show the cached project context
+    Context {
+        /// Refresh the context from GitLab before showing it
+        #[arg(long)]
+        update: bool,
+    },
+    /// List available projects
+    Projects,
+    /// Authenticate with GitLab (into the profile given by --profile)
+    Login,
+    /// List or switch GitLab instance profiles
+    Profile {
+        #[command(subcommand)]
+        action: Option<ProfileAction>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileAction {
+    /// List configured profiles
+    List,
+    /// Make a profile the default
+    Use {
+        /// Profile name
+        name: String,
+    },
+}
+
+/// Error raised when a command needs credentials or a project that are not configured
+#[derive(Debug)]
+pub struct NotConfigured(pub &'static str);
+
+impl fmt::Display for NotConfigured {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for NotConfigured {}
+
+/// Run a single subcommand and map the outcome to a process exit code
+pub async fn run(profile: Option<String>, project: Option<String>, dry_run: bool, command: Commands) -> ExitCode {
+    audit::begin(&std::env::args().skip(1).collect::<Vec<_>>().join(" "), None);
+    // The audit log is local and covers every profile, so it needs no login; --project filters it
+    if let Commands::Audit { date, since, until, tool } = command {
+        let filter = AuditFilter { since: date.or(since), until: date.or(until), tool, project };
+        return match audit::print(&filter) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => report_error(&e),
+        };
+    }
+
+    let mut session = match KenSession::without_mcp(profile, project, dry_run) {
+        Ok(session) => session,
+        Err(e) => return report_error(&e),
+    };
+
+    let result = execute(&mut session, command).await;
+    session.cleanup().await;
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // The held-back write has already been printed
+        Err(e) if matches!(e.downcast_ref(), Some(GitLabError::DryRun)) => {
+            println!("🧪 Nothing was changed.");
+            ExitCode::SUCCESS
+        }
+        Err(e) => report_error(&e),
+    }
+}
+
+async fn execute(session: &mut KenSession, command: Commands) -> Result<()> {
+    match command {
+        Commands::Login => {
+            KenSession::save_login(&session.profile).await?;
+            println!("✅ Login successful!");
+            Ok(())
+        }
+        Commands::Profile { action } => match action {
+            None | Some(ProfileAction::List) => session.list_profiles(),
+            Some(ProfileAction::Use { name }) => session.use_profile(&name).await,
+        },
+        Commands::Projects => {
+            let config = session.require_config()?;
+            session.list_projects(config).await
+        }
+        Commands::Context { update } => {
+            if update {
+                session.update_context().await?;
+            }
+            session.show_context()
+        }
+        Commands::Workload { live } => {
+            let (config, _) = session.require_project()?;
+            if live {
+                session.analyze_workload_direct(config).await
+            } else {
+                session.analyze_workload_from_context(config).await
+            }
+        }
+        Commands::Issue { description, yes } => session.create_issue_from_description(&description, yes).await,
+        Commands::Suggest { iid, assign } => session.suggest_assignee(iid, assign).await,
+        Commands::Duplicates { iid } => session.find_duplicates(iid).await,
+        Commands::Summarize { iid, post } => session.summarize_issue(iid, post).await,
+        Commands::Summary { user, days } => session.summarize_developer(&user, days).await,
+        Commands::Report { daily, weekly: _, format } => {
+            let period = if daily { Period::Daily } else { Period::Weekly };
+            session.progress_report(period, format).await
+        }
+        Commands::Audit { .. } => unreachable!("handled before the session is created"),
+        Commands::Bot { listen } => bot::serve(session.require_config()?.clone(), listen).await,
+    }
+}
+
+fn report_error(error: &anyhow::Error) -> ExitCode {
+    eprintln!("❌ {}", error);
+    if error.downcast_ref::<NotConfigured>().is_some() {
+        ExitCode::from(EXIT_NOT_CONFIGURED)
+    } else {
+        ExitCode::from(EXIT_FAILURE)
+    }
+}
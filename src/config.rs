@@ -1,22 +1,301 @@
 use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use mcp_core::types::ToolsListResponse;
+use crate::agent;
+use crate::credentials::{self, TokenStore};
+use crate::gitlab_client::{GitLabClient, GitLabError};
 
+/// GitLab URL used when only `KEN_GITLAB_TOKEN` is set and there is no config file.
+/// With profiles, `KEN_GITLAB_TOKEN` is only used for the ones on this URL.
+const URL_ENV: &str = "KEN_GITLAB_URL";
+/// Profile used when none has been named
+pub const DEFAULT_PROFILE: &str = "default";
+/// Lines of REPL history kept in `~/.ken/history` unless `history_size` says otherwise
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Settings for one GitLab instance, stored as a `[profiles.<name>]` table
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
+    /// Name of the profile these settings were loaded from
+    #[serde(skip)]
+    pub profile: String,
     pub gitlab_url: String,
+    /// Kept in the keyring or the credentials file, never in config.toml.
+    /// Older configs stored it here; those are migrated on load.
+    #[serde(default, skip_serializing)]
     pub api_token: String,
     pub default_project_id: Option<String>,
+    /// Upper bound on pages fetched per list request; unlimited when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pages: Option<u32>,
+    /// Retries for rate-limited or failed GitLab requests (default 3)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// GitLab requests allowed in flight at once (default 4)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<usize>,
+    /// The shared `[llm]` section with environment overrides applied
+    #[serde(skip)]
+    pub llm: LlmConfig,
+    /// The shared `[tools]` section
+    #[serde(skip)]
+    pub tools: ToolsConfig,
+    /// Set for `--dry-run` sessions: GitLab writes are printed and audited instead of sent
+    #[serde(skip)]
+    pub dry_run: bool,
+}
+
+/// LLM settings from the `[llm]` section; unset fields fall back to the built-in defaults
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LlmConfig {
+    /// Backend to use; `openai` covers any OpenAI-compatible server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Prefer `KEN_LLM_API_KEY` over storing the key here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    /// Model context window in tokens; chat history is trimmed to fit it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<usize>,
+}
+
+/// `[tools]` section: which GitLab MCP tools the agent is given
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ToolsConfig {
+    /// Tool profile in use; every tool the server offers when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// `[tools.profiles.<name>]` tables, next to or in place of the built-in `read-only` and `triage`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, ToolFilter>,
+}
+
+/// Tool names or globs to allow and deny. `*` matches any run of characters and `?` any one.
+/// An empty `allow` allows every tool, and `deny` wins over `allow`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ToolFilter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+/// Tool profiles available without defining them in config.toml
+const BUILTIN_TOOL_PROFILES: [&str; 2] = ["read-only", "triage"];
+/// gitlab-mcp tools that triage needs on top of reading: editing issues, comments and issue links
+const TRIAGE_TOOLS: [&str; 6] = [
+    "create_issue", "update_issue", "create_note", "create_issue_note", "update_issue_note", "create_issue_link",
+];
+
+impl ToolFilter {
+    pub fn permits(&self, name: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|pattern| glob_match(pattern, name)))
+            && !self.deny.iter().any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Built-in profiles: `read-only` allows only tools that read, `triage` adds issue editing and comments
+    fn builtin(name: &str) -> Option<Self> {
+        let read_only = agent::READ_ONLY_PREFIXES.iter().map(|prefix| format!("{}*", prefix))
+            .chain(agent::READ_ONLY_TOOLS.iter().map(|tool| tool.to_string()));
+        match name {
+            "read-only" => Some(Self { allow: read_only.collect(), deny: Vec::new() }),
+            "triage" => Some(Self {
+                allow: read_only.chain(TRIAGE_TOOLS.iter().map(|tool| tool.to_string())).collect(),
+                deny: Vec::new(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl ToolsConfig {
+    /// Keep the tools the active profile permits. Fails on an unknown profile rather than
+    /// handing the agent every tool.
+    pub fn apply(&self, mut tools: ToolsListResponse) -> Result<ToolsListResponse> {
+        let Some(name) = &self.profile else {
+            return Ok(tools);
+        };
+        let filter = self.profiles.get(name).cloned()
+            .or_else(|| ToolFilter::builtin(name))
+            .with_context(|| {
+                let mut names = BUILTIN_TOOL_PROFILES.to_vec();
+                names.extend(self.profiles.keys().map(String::as_str).filter(|n| !BUILTIN_TOOL_PROFILES.contains(n)));
+                format!("No tool profile named '{}'. Available profiles: {}", name, names.join(", "))
+            })?;
+        tools.tools.retain(|tool| filter.permits(&tool.name));
+        Ok(tools)
+    }
+}
+
+/// Match `name` against a glob where `*` is any run of characters and `?` any one character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was and how much of the name it has taken, to backtrack to on a mismatch
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl LlmConfig {
+    /// Apply `KEN_LLM_*` environment variables on top of the file settings
+    pub fn with_env_overrides(mut self) -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        
+        if let Some(provider) = var("KEN_LLM_PROVIDER") {
+            self.provider = Some(provider);
+        }
+        if let Some(base_url) = var("KEN_LLM_BASE_URL") {
+            self.base_url = Some(base_url);
+        }
+        if let Some(model) = var("KEN_LLM_MODEL") {
+            self.model = Some(model);
+        }
+        if let Some(api_key) = var("KEN_LLM_API_KEY") {
+            self.api_key = Some(api_key);
+        }
+        if let Some(temperature) = var("KEN_LLM_TEMPERATURE") {
+            match temperature.trim().parse() {
+                Ok(temperature) => self.temperature = Some(temperature),
+                Err(_) => eprintln!("⚠️  Ignoring invalid KEN_LLM_TEMPERATURE: {}", temperature),
+            }
+        }
+        if let Some(max_tokens) = var("KEN_LLM_MAX_TOKENS") {
+            match max_tokens.trim().parse() {
+                Ok(max_tokens) => self.max_tokens = Some(max_tokens),
+                Err(_) => eprintln!("⚠️  Ignoring invalid KEN_LLM_MAX_TOKENS: {}", max_tokens),
+            }
+        }
+        if let Some(context_window) = var("KEN_LLM_CONTEXT_WINDOW") {
+            match context_window.trim().parse() {
+                Ok(context_window) => self.context_window = Some(context_window),
+                Err(_) => eprintln!("⚠️  Ignoring invalid KEN_LLM_CONTEXT_WINDOW: {}", context_window),
+            }
+        }
+        
+        self
+    }
+}
+
+/// Contents of `~/.ken/config.toml`: named profiles and the one used by default
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    /// Lines of REPL history to keep; 0 turns persistent history off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_size: Option<usize>,
+    #[serde(default, skip_serializing_if = "is_default_llm")]
+    pub llm: LlmConfig,
+    #[serde(default, skip_serializing_if = "is_default_tools")]
+    pub tools: ToolsConfig,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Config>,
+}
+
+fn is_default_llm(llm: &LlmConfig) -> bool {
+    *llm == LlmConfig::default()
+}
+
+fn is_default_tools(tools: &ToolsConfig) -> bool {
+    *tools == ToolsConfig::default()
+}
+
+impl ConfigFile {
+    /// Read config.toml, converting the single-instance layout of older versions into a `default` profile
+    pub fn load() -> Result<Self> {
+        let path = Config::config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        
+        let contents = fs::read_to_string(&path)?;
+        let table: toml::Table = toml::from_str(&contents)?;
+        if !table.contains_key("gitlab_url") {
+            let mut file: ConfigFile = table.try_into()?;
+            for (name, config) in file.profiles.iter_mut() {
+                config.profile = name.clone();
+            }
+            return Ok(file);
+        }
+        
+        let mut config: Config = table.try_into()?;
+        config.profile = DEFAULT_PROFILE.to_string();
+        // Saving drops a plain-text token, so move it into the credential store first
+        if !config.api_token.is_empty() {
+            let store = config.store_token()?;
+            println!("🔐 Moved GitLab token out of config.toml into {}", store);
+            config.api_token.clear();
+        }
+        
+        let file = ConfigFile {
+            active_profile: Some(DEFAULT_PROFILE.to_string()),
+            history_size: None,
+            llm: LlmConfig::default(),
+            tools: ToolsConfig::default(),
+            profiles: BTreeMap::from([(DEFAULT_PROFILE.to_string(), config)]),
+        };
+        file.save()?;
+        println!("📦 Converted ~/.ken/config.toml to profiles (current settings are now profile '{}')", DEFAULT_PROFILE);
+        Ok(file)
+    }
+
+    pub fn history_size(&self) -> usize {
+        self.history_size.unwrap_or(DEFAULT_HISTORY_SIZE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Config::config_path()?;
+        let contents = toml::to_string_pretty(self)?;
+        write_private(&path, contents.as_bytes())
+    }
+
+    /// Name of the profile used when no `--profile` is given
+    pub fn active(&self) -> &str {
+        self.active_profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
 }
 
 impl Config {
     pub fn new(gitlab_url: String, api_token: String) -> Self {
         Self {
+            profile: DEFAULT_PROFILE.to_string(),
             gitlab_url,
             api_token,
             default_project_id: None,
+            max_pages: None,
+            max_retries: None,
+            concurrency: None,
+            llm: LlmConfig::default(),
+            tools: ToolsConfig::default(),
+            dry_run: false,
         }
     }
 
@@ -26,33 +305,167 @@ impl Config {
         
         // Create directory if it doesn't exist
         if !config_dir.exists() {
-            fs::create_dir_all(&config_dir)?;
+            create_private_dir(&config_dir)?;
         }
         
         Ok(config_dir.join("config.toml"))
     }
 
-    pub fn load() -> Result<Self> {
-        let path = Self::config_path()?;
+    /// Load the named profile, or the active one when `name` is `None`
+    pub fn load_profile(name: Option<&str>) -> Result<Self> {
+        let file = ConfigFile::load()?;
+        let name = name.unwrap_or(file.active()).to_string();
+        let llm = file.llm.clone().with_env_overrides();
         
-        if !path.exists() {
-            anyhow::bail!("No configuration found. Please run 'ken auth login' first.");
+        let Some(mut config) = file.profiles.get(&name).cloned() else {
+            // CI can run with environment variables alone
+            if file.profiles.is_empty()
+                && let Some(token) = credentials::token_from_env()
+            {
+                let gitlab_url = std::env::var(URL_ENV).unwrap_or_else(|_| "https://gitlab.com".to_string());
+                let mut config = Config::new(gitlab_url, token);
+                config.profile = name;
+                config.llm = llm;
+                config.tools = file.tools;
+                return Ok(config);
+            }
+            if file.profiles.is_empty() {
+                anyhow::bail!("No configuration found. Please run 'ken login' first.");
+            }
+            anyhow::bail!(
+                "No profile named '{}'. Available profiles: {}",
+                name,
+                file.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+        };
+        
+        if !config.api_token.is_empty() {
+            config.migrate_plaintext_token()?;
         }
         
-        let contents = fs::read_to_string(&path)?;
-        let config: Config = toml::from_str(&contents)?;
+        // KEN_GITLAB_TOKEN only stands in for a profile on the instance KEN_GITLAB_URL names,
+        // so one instance's token is never sent to another
+        let token = match credentials::token_from_env().filter(|_| env_url_matches(&config.gitlab_url)) {
+            Some(token) => token,
+            None => match credentials::load_token(config.token_account())? {
+                Some((token, _)) => token,
+                None => config.migrate_url_keyed_token()?
+                    .map(|(token, _)| token)
+                    .with_context(|| format!(
+                        "No GitLab token found for profile '{}'. Run 'ken --profile {} login', or set KEN_GITLAB_TOKEN together with KEN_GITLAB_URL={}.",
+                        config.profile, config.profile, config.gitlab_url
+                    ))?,
+            },
+        };
+        config.api_token = token;
+        config.llm = llm;
+        config.tools = file.tools;
         
         Ok(config)
     }
 
+    /// Take what a fresh login doesn't ask for from config.toml: the shared `[llm]` (with
+    /// environment overrides) and `[tools]` sections, and the tuning of the profile being replaced
+    pub fn keep_file_settings(&mut self, file: &ConfigFile) {
+        self.llm = file.llm.clone().with_env_overrides();
+        self.tools = file.tools.clone();
+        if let Some(previous) = file.profiles.get(&self.profile) {
+            self.max_pages = previous.max_pages;
+            self.max_retries = previous.max_retries;
+            self.concurrency = previous.concurrency;
+        }
+    }
+
+    /// Write this profile into config.toml, leaving the other profiles untouched
     pub fn save(&self) -> Result<()> {
-        let path = Self::config_path()?;
-        let contents = toml::to_string_pretty(self)?;
-        fs::write(path, contents)?;
+        let mut file = ConfigFile::load()?;
+        if file.active_profile.is_none() {
+            file.active_profile = Some(self.profile.clone());
+        }
+        file.profiles.insert(self.profile.clone(), self.clone());
+        file.save()
+    }
+
+    /// Make this profile the default for future runs
+    pub fn set_active(&self) -> Result<()> {
+        let mut file = ConfigFile::load()?;
+        file.active_profile = Some(self.profile.clone());
+        file.save()
+    }
+
+    /// Switch the LLM model for this session and remember it in the `[llm]` section.
+    /// Changing provider also drops the saved endpoint, which belonged to the old provider.
+    pub fn set_model(&mut self, provider: Option<&str>, model: &str) -> Result<()> {
+        let mut file = ConfigFile::load()?;
+        for llm in [&mut file.llm, &mut self.llm] {
+            if let Some(provider) = provider
+                && llm.provider.as_deref() != Some(provider)
+            {
+                llm.provider = Some(provider.to_string());
+                llm.base_url = None;
+            }
+            llm.model = Some(model.to_string());
+        }
+        file.save()
+    }
+
+    /// Delete this profile and its stored token
+    pub fn remove(&self) -> Result<()> {
+        self.delete_token()?;
         
+        let mut file = ConfigFile::load()?;
+        file.profiles.remove(&self.profile);
+        if file.active() == self.profile {
+            file.active_profile = file.profiles.keys().next().cloned();
+        }
+        
+        if file.profiles.is_empty() {
+            let path = Self::config_path()?;
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            Ok(())
+        } else {
+            file.save()
+        }
+    }
+
+    /// Key for this profile's token in the credential store
+    pub fn token_account(&self) -> &str {
+        &self.profile
+    }
+
+    /// Save the token to the keyring, or the credentials file when no keyring is available
+    pub fn store_token(&self) -> Result<TokenStore> {
+        credentials::store_token(self.token_account(), &self.api_token)
+    }
+
+    /// Remove the stored token for this profile
+    pub fn delete_token(&self) -> Result<()> {
+        credentials::delete_token(self.token_account())
+    }
+
+    /// Move a token written by older versions out of config.toml
+    fn migrate_plaintext_token(&self) -> Result<()> {
+        let store = self.store_token()?;
+        self.save()?;
+        println!("🔐 Moved GitLab token out of config.toml into {}", store);
         Ok(())
     }
 
+    /// Before profiles, stored tokens were keyed by GitLab URL; re-key them by profile name
+    fn migrate_url_keyed_token(&mut self) -> Result<Option<(String, TokenStore)>> {
+        let url_account = self.gitlab_url.trim_end_matches('/').to_string();
+        let Some((token, _)) = credentials::load_token(&url_account)? else {
+            return Ok(None);
+        };
+        
+        self.api_token = token.clone();
+        let store = self.store_token()?;
+        credentials::delete_token(&url_account)?;
+        Ok(Some((token, store)))
+    }
+
     pub fn prompt_for_login() -> Result<Self> {
         println!("GitLab Authentication Setup");
         println!("----------------------------");
@@ -100,21 +513,156 @@ impl Config {
 
     pub async fn verify(&self) -> Result<()> {
         // Make a simple API call to verify the token works
-        let client = reqwest::Client::new();
-        let response = client
-            .get(format!("{}/api/v4/user", self.gitlab_url))
-            .header("PRIVATE-TOKEN", &self.api_token)
-            .send()
-            .await?;
-        
-        if response.status().is_success() {
-            let user: serde_json::Value = response.json().await?;
-            if let Some(username) = user.get("username").and_then(|u| u.as_str()) {
-                println!("✓ Successfully authenticated as: {}", username);
+        match GitLabClient::new(self).current_user().await {
+            Ok(user) => {
+                println!("✓ Successfully authenticated as: {}", user.username);
+                Ok(())
             }
-            Ok(())
-        } else {
-            anyhow::bail!("Authentication failed. Please check your token and URL.");
+            Err(GitLabError::Unauthorized) => {
+                anyhow::bail!("Authentication failed. Please check your token and URL.");
+            }
+            Err(e) => Err(e.into()),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Whether `KEN_GITLAB_URL` names the instance at `gitlab_url`
+fn env_url_matches(gitlab_url: &str) -> bool {
+    std::env::var(URL_ENV).is_ok_and(|url| {
+        url.trim().trim_end_matches('/').eq_ignore_ascii_case(gitlab_url.trim_end_matches('/'))
+    })
+}
+
+/// Write a file readable only by the current user
+pub fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // `mode` only applies to new files, so tighten files created by older versions too
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(data)?;
+    }
+    #[cfg(not(unix))]
+    fs::write(path, data)?;
+    
+    Ok(())
+}
+
+pub fn create_private_dir(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(path)?;
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(path)?;
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_keeps_tools_and_profile_tuning() {
+        let mut previous = Config::new("https://gitlab.example.com".to_string(), String::new());
+        previous.profile = "work".to_string();
+        previous.max_pages = Some(5);
+        previous.concurrency = Some(2);
+        let file = ConfigFile {
+            tools: ToolsConfig { profile: Some("read-only".to_string()), profiles: BTreeMap::new() },
+            profiles: BTreeMap::from([("work".to_string(), previous)]),
+            ..ConfigFile::default()
+        };
+        
+        let mut login = Config::new("https://gitlab.example.com".to_string(), "token".to_string());
+        login.profile = "work".to_string();
+        login.keep_file_settings(&file);
+        assert_eq!(login.tools, file.tools);
+        assert_eq!(login.llm, file.llm.clone().with_env_overrides());
+        assert_eq!((login.max_pages, login.max_retries, login.concurrency), (Some(5), None, Some(2)));
+        
+        // A new profile has no tuning to keep, but still gets the shared sections
+        let mut login = Config::new("https://gitlab.example.com".to_string(), "token".to_string());
+        login.profile = "other".to_string();
+        login.keep_file_settings(&file);
+        assert_eq!(login.tools, file.tools);
+        assert_eq!(login.max_pages, None);
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob_match("get_*", "get_issue"));
+        assert!(glob_match("get_*", "get_"));
+        assert!(!glob_match("get_*", "list_issues"));
+        assert!(glob_match("*_issue", "update_issue"));
+        assert!(glob_match("*wiki*", "update_wiki_page"));
+        assert!(glob_match("create_?ote", "create_note"));
+        assert!(!glob_match("create_?ote", "create_issue_note"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("delete_issue", "delete_issues"), "no pattern means exact match");
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let filter = ToolFilter {
+            allow: vec!["*_issue".to_string(), "list_*".to_string()],
+            deny: vec!["delete_*".to_string()],
+        };
+        assert!(filter.permits("update_issue"));
+        assert!(filter.permits("list_projects"));
+        assert!(!filter.permits("delete_issue"));
+        assert!(!filter.permits("create_merge_request"));
+        
+        let deny_only = ToolFilter { allow: Vec::new(), deny: vec!["*wiki*".to_string(), "cancel_pipeline".to_string()] };
+        assert!(deny_only.permits("create_merge_request"));
+        assert!(!deny_only.permits("update_wiki_page"));
+        assert!(!deny_only.permits("cancel_pipeline"));
+    }
+
+    fn tools(names: &[&str]) -> ToolsListResponse {
+        serde_json::from_value(serde_json::json!({
+            "tools": names.iter()
+                .map(|name| serde_json::json!({ "name": name, "inputSchema": { "type": "object" } }))
+                .collect::<Vec<_>>(),
+        })).unwrap()
+    }
+
+    fn kept(config: &ToolsConfig, names: &[&str]) -> Vec<String> {
+        config.apply(tools(names)).unwrap().tools.into_iter().map(|tool| tool.name).collect()
+    }
+
+    #[test]
+    fn builtin_profiles() {
+        let offered = [
+            "get_issue", "list_issues", "search_repositories", "mr_discussions", "my_issues",
+            "create_issue", "update_issue", "create_issue_note", "create_issue_link",
+            "delete_issue", "update_wiki_page", "cancel_pipeline", "merge_merge_request", "create_or_update_file",
+        ];
+        let profile = |name: &str| ToolsConfig { profile: Some(name.to_string()), profiles: BTreeMap::new() };
+        
+        assert_eq!(kept(&profile("read-only"), &offered), ["get_issue", "list_issues", "search_repositories", "mr_discussions", "my_issues"]);
+        assert_eq!(kept(&profile("triage"), &offered), [
+            "get_issue", "list_issues", "search_repositories", "mr_discussions", "my_issues",
+            "create_issue", "update_issue", "create_issue_note", "create_issue_link",
+        ]);
+        assert_eq!(kept(&ToolsConfig::default(), &offered).len(), offered.len());
+        assert!(profile("nonexistent").apply(tools(&offered)).is_err(), "an unknown profile must not allow everything");
+    }
+
+    #[test]
+    fn configured_profiles_replace_builtins() {
+        let config = ToolsConfig {
+            profile: Some("read-only".to_string()),
+            profiles: BTreeMap::from([("read-only".to_string(), ToolFilter { allow: vec!["get_issue".to_string()], deny: Vec::new() })]),
+        };
+        assert_eq!(kept(&config, &["get_issue", "list_issues"]), ["get_issue"]);
+    }
+}
@@ -1,8 +1,16 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::fs;
+use std::future::Future;
 use std::path::PathBuf;
+use crate::gitlab_client::{GitLabClient, GitLabError, GitLabIssue, GitLabMR, GitLabResult};
+
+/// Closed issues cached for duplicate detection, most recently updated first
+pub const RECENTLY_CLOSED_ISSUES: u32 = 100;
+/// Characters of each issue description kept in the cache
+const DESCRIPTION_LIMIT: usize = 1000;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ProjectContext {
@@ -12,9 +20,14 @@ pub struct ProjectContext {
     pub milestones: Vec<ProjectMilestone>,
     pub teams: HashMap<String, Vec<String>>, // team name -> list of usernames
     pub hot_issues: Vec<HotIssue>,
+    #[serde(default)]
+    pub recently_closed: Vec<HotIssue>,
     pub issue_patterns: IssuePatterns,
     pub workload_data: WorkloadData,
     pub last_updated: Option<String>,
+    /// Fetches that still failed after retries; their sections are incomplete
+    #[serde(default)]
+    pub fetch_errors: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -60,6 +73,9 @@ pub struct HotIssue {
     pub state: String,
     pub updated_recently: bool,
     pub priority: Option<String>,
+    /// Start of the description, kept for open and recently closed issues only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -86,6 +102,51 @@ pub struct ProjectMilestone {
     pub due_date: Option<String>,
 }
 
+/// Prints a line as each concurrent fetch finishes so long refreshes show progress
+struct FetchProgress {
+    done: AtomicUsize,
+    total: usize,
+}
+
+impl FetchProgress {
+    fn new(total: usize) -> Self {
+        Self { done: AtomicUsize::new(0), total }
+    }
+    
+    async fn track<T>(&self, what: &str, fetch: impl Future<Output = GitLabResult<Vec<T>>>) -> GitLabResult<Vec<T>> {
+        let result = fetch.await;
+        let done = self.done.fetch_add(1, Ordering::SeqCst) + 1;
+        match &result {
+            Ok(items) => println!("   ✓ {} ({}) [{}/{}]", what, items.len(), done, self.total),
+            Err(e) => println!("   ✗ {}: {} [{}/{}]", what, e, done, self.total),
+        }
+        result
+    }
+}
+
+impl HotIssue {
+    fn from_issue(issue: &GitLabIssue, assignee: Option<String>) -> Self {
+        HotIssue {
+            id: issue.iid as u32,
+            title: issue.title.clone(),
+            assignee,
+            labels: issue.labels.clone(),
+            state: issue.state.clone(),
+            updated_recently: true,
+            priority: None,
+            description: None,
+        }
+    }
+    
+    /// Entry for `hot_issues` and `recently_closed`, with the start of the description for searching
+    fn with_description(issue: &GitLabIssue) -> Self {
+        HotIssue {
+            description: issue.description.as_deref().map(|d| d.chars().take(DESCRIPTION_LIMIT).collect()),
+            ..Self::from_issue(issue, issue.assignee_usernames().first().map(|s| s.to_string()))
+        }
+    }
+}
+
 impl ProjectContext {
     pub fn new(project_id: String) -> Self {
         Self {
@@ -95,28 +156,29 @@ impl ProjectContext {
             milestones: Vec::new(),
             teams: HashMap::new(),
             hot_issues: Vec::new(),
+            recently_closed: Vec::new(),
             issue_patterns: IssuePatterns::default(),
             workload_data: WorkloadData::default(),
             last_updated: None,
+            fetch_errors: Vec::new(),
         }
     }
 
-    pub fn context_path(project_id: &str) -> Result<PathBuf> {
+    /// Cached contexts live under `~/.ken/contexts/<profile>/` so projects on different instances never collide
+    pub fn context_path(profile: &str, project_id: &str) -> Result<PathBuf> {
         let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Failed to get home directory"))?;
-        let context_dir = home.join(".ken").join("contexts");
+        let context_dir = home.join(".ken").join("contexts").join(sanitize_file_name(profile));
         
         // Create directory if it doesn't exist
         if !context_dir.exists() {
             fs::create_dir_all(&context_dir)?;
         }
         
-        // Sanitize project ID for filename
-        let safe_project_id = project_id.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
-        Ok(context_dir.join(format!("{}.json", safe_project_id)))
+        Ok(context_dir.join(format!("{}.json", sanitize_file_name(project_id))))
     }
 
-    pub fn load(project_id: &str) -> Result<Self> {
-        let path = Self::context_path(project_id)?;
+    pub fn load(profile: &str, project_id: &str) -> Result<Self> {
+        let path = Self::context_path(profile, project_id)?;
         
         if !path.exists() {
             return Ok(Self::new(project_id.to_string()));
@@ -128,386 +190,184 @@ impl ProjectContext {
         Ok(context)
     }
 
-    pub fn save(&self) -> Result<()> {
-        let path = Self::context_path(&self.project_id)?;
+    pub fn save(&self, profile: &str) -> Result<()> {
+        let path = Self::context_path(profile, &self.project_id)?;
         let contents = serde_json::to_string_pretty(self)?;
         fs::write(path, contents)?;
         
         Ok(())
     }
 
+    /// The cached project context, fetched and saved first if there is none yet
+    pub async fn cached(config: &crate::config::Config, project_id: &str) -> Result<Self> {
+        if let Ok(context) = Self::load(&config.profile, project_id)
+            && context.last_updated.is_some() {
+            return Ok(context);
+        }
+        println!("📦 No cached context yet; fetching labels, milestones, members and workload...");
+        let context = Self::fetch_from_gitlab(config, project_id).await?;
+        if let Err(e) = context.save(&config.profile) {
+            println!("⚠️  Context fetched but failed to save: {}", e);
+        }
+        Ok(context)
+    }
+
     pub async fn fetch_from_gitlab(config: &crate::config::Config, project_id: &str) -> Result<Self> {
         let mut context = Self::new(project_id.to_string());
+        let client = GitLabClient::new(config);
+        let mut errors = Vec::new();
+        
+        println!("🔄 Fetching project data ({} requests in parallel)...", client.concurrency());
+        let progress = FetchProgress::new(6);
+        
+        // Workload is derived from one listing of open issues and MRs rather than
+        // two requests per member, so six listings cover the whole context
+        let closed_query = [("state", "closed"), ("order_by", "updated_at")];
+        let (labels, users, milestones, issues, mrs, closed) = tokio::join!(
+            progress.track("labels", Self::fetch_labels(&client, project_id)),
+            progress.track("project members", Self::fetch_project_members(&client, project_id)),
+            progress.track("milestones", Self::fetch_milestones(&client, project_id)),
+            progress.track("open issues", client.list_issues(project_id, &[("state", "opened")])),
+            progress.track("open merge requests", client.list_merge_requests(project_id, &[("state", "opened")])),
+            progress.track("recently closed issues", client.list_recent_issues(project_id, &closed_query, RECENTLY_CLOSED_ISSUES)),
+        );
         
-        let client = reqwest::Client::new();
-        let base_url = &config.gitlab_url;
-        let token = &config.api_token;
-        
-        // Fetch labels
-        if let Ok(labels) = Self::fetch_labels(&client, base_url, token, project_id).await {
-            context.labels = labels;
+        match labels {
+            Ok(labels) => context.labels = labels,
+            Err(e) => Self::record_failure(&mut errors, "labels", e)?,
         }
-        
-        // Fetch project members
-        if let Ok(users) = Self::fetch_project_members(&client, base_url, token, project_id).await {
-            context.users = users;
+        match users {
+            Ok(users) => context.users = users,
+            Err(e) => Self::record_failure(&mut errors, "project members", e)?,
         }
-        
-        // Fetch milestones
-        if let Ok(milestones) = Self::fetch_milestones(&client, base_url, token, project_id).await {
-            context.milestones = milestones;
+        match milestones {
+            Ok(milestones) => context.milestones = milestones,
+            Err(e) => Self::record_failure(&mut errors, "milestones", e)?,
         }
+        let issues = issues.or_else(|e| Self::record_failure(&mut errors, "open issues", e).map(|_| Vec::new()))?;
+        let mrs = mrs.or_else(|e| Self::record_failure(&mut errors, "open merge requests", e).map(|_| Vec::new()))?;
         
-        // Fetch all open issues for activity tracking
-        if let Ok(issues) = Self::fetch_all_open_issues(&client, base_url, token, project_id).await {
-            context.hot_issues = issues;
+        match closed {
+            Ok(closed) => context.recently_closed = closed.iter().map(HotIssue::with_description).collect(),
+            Err(e) => Self::record_failure(&mut errors, "recently closed issues", e)?,
         }
         
-        // Fetch comprehensive workload data for each user
-        context.workload_data = Self::fetch_workload_data(&client, base_url, token, project_id, &context.users).await?;
+        context.hot_issues = issues.iter().map(HotIssue::with_description).collect();
+        context.workload_data = Self::build_workload(&issues, &mrs);
         
+        println!("✅ Workload data complete: {} active users, {} total issues", 
+            context.workload_data.user_assignments.len(), context.workload_data.total_open_issues);
+        
+        context.fetch_errors = errors;
         context.update_timestamp();
         Ok(context)
     }
     
-    async fn fetch_labels(client: &reqwest::Client, base_url: &str, token: &str, project_id: &str) -> Result<Vec<ProjectLabel>> {
-        let url = format!("{}/api/v4/projects/{}/labels?per_page=100", base_url, urlencoding::encode(project_id));
-        
-        let response = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", token)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            return Ok(Vec::new());
+    /// Note a failed fetch so it is reported instead of cached as empty data.
+    /// A rejected token fails every request, so that aborts the whole refresh.
+    fn record_failure(errors: &mut Vec<String>, what: &str, error: GitLabError) -> Result<()> {
+        if matches!(error, GitLabError::Unauthorized) {
+            return Err(error.into());
         }
-        
-        let labels: Vec<serde_json::Value> = response.json().await?;
+        errors.push(format!("{}: {}", what, error));
+        Ok(())
+    }
+    
+    async fn fetch_labels(client: &GitLabClient, project_id: &str) -> GitLabResult<Vec<ProjectLabel>> {
+        let labels = client.list_labels(project_id).await?;
         
         Ok(labels.into_iter().map(|label| {
             ProjectLabel {
-                name: label.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string(),
-                color: label.get("color").and_then(|c| c.as_str()).map(|s| s.to_string()),
-                description: label.get("description").and_then(|d| d.as_str()).map(|s| s.to_string()),
+                name: label.name,
+                color: label.color,
+                description: label.description,
                 usage_count: None,
             }
         }).collect())
     }
     
-    async fn fetch_project_members(client: &reqwest::Client, base_url: &str, token: &str, project_id: &str) -> Result<Vec<ProjectUser>> {
-        let url = format!("{}/api/v4/projects/{}/members/all?per_page=100", base_url, urlencoding::encode(project_id));
-        
-        let response = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", token)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            return Ok(Vec::new());
-        }
-        
-        let members: Vec<serde_json::Value> = response.json().await?;
+    async fn fetch_project_members(client: &GitLabClient, project_id: &str) -> GitLabResult<Vec<ProjectUser>> {
+        let members = client.list_members(project_id).await?;
         
         Ok(members.into_iter().map(|member| {
             ProjectUser {
-                username: member.get("username").and_then(|u| u.as_str()).unwrap_or("").to_string(),
-                name: member.get("name").and_then(|n| n.as_str()).map(|s| s.to_string()),
-                email: member.get("email").and_then(|e| e.as_str()).map(|s| s.to_string()),
-                role: member.get("access_level").and_then(|a| a.as_u64()).map(|level| {
-                    match level {
-                        10 => "Guest",
-                        20 => "Reporter", 
-                        30 => "Developer",
-                        40 => "Maintainer",
-                        50 => "Owner",
-                        _ => "Member"
-                    }.to_string()
-                }),
+                username: member.username,
+                name: Some(member.name).filter(|n| !n.is_empty()),
+                email: member.email,
+                role: Some(member.role_name),
             }
         }).collect())
     }
     
-    async fn fetch_milestones(client: &reqwest::Client, base_url: &str, token: &str, project_id: &str) -> Result<Vec<ProjectMilestone>> {
-        let url = format!("{}/api/v4/projects/{}/milestones?per_page=100", base_url, urlencoding::encode(project_id));
-        
-        let response = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", token)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            return Ok(Vec::new());
-        }
-        
-        let milestones: Vec<serde_json::Value> = response.json().await?;
+    async fn fetch_milestones(client: &GitLabClient, project_id: &str) -> GitLabResult<Vec<ProjectMilestone>> {
+        let milestones = client.list_milestones(project_id).await?;
         
         Ok(milestones.into_iter().map(|milestone| {
             ProjectMilestone {
-                title: milestone.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
-                state: milestone.get("state").and_then(|s| s.as_str()).unwrap_or("").to_string(),
-                description: milestone.get("description").and_then(|d| d.as_str()).map(|s| s.to_string()),
-                due_date: milestone.get("due_date").and_then(|d| d.as_str()).map(|s| s.to_string()),
-            }
-        }).collect())
-    }
-    
-    async fn fetch_all_open_issues(client: &reqwest::Client, base_url: &str, token: &str, project_id: &str) -> Result<Vec<HotIssue>> {
-        let url = format!("{}/api/v4/projects/{}/issues?state=opened&per_page=100", base_url, urlencoding::encode(project_id));
-        
-        let response = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", token)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            return Ok(Vec::new());
-        }
-        
-        let issues: Vec<serde_json::Value> = response.json().await?;
-        
-        Ok(issues.into_iter().map(|issue| {
-            let labels: Vec<String> = issue.get("labels")
-                .and_then(|l| l.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
-                .unwrap_or_default();
-            
-            // Check if assignees array exists and get the first one
-            let assignee = issue.get("assignees")
-                .and_then(|a| a.as_array())
-                .and_then(|arr| arr.first())
-                .and_then(|a| a.get("username"))
-                .and_then(|u| u.as_str())
-                .map(|s| s.to_string())
-                .or_else(|| {
-                    // Fallback to single assignee field
-                    issue.get("assignee")
-                        .and_then(|a| a.get("username"))
-                        .and_then(|u| u.as_str())
-                        .map(|s| s.to_string())
-                });
-                
-            HotIssue {
-                id: issue.get("iid").and_then(|i| i.as_u64()).unwrap_or(0) as u32,
-                title: issue.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
-                assignee,
-                labels,
-                state: issue.get("state").and_then(|s| s.as_str()).unwrap_or("").to_string(),
-                updated_recently: true,
-                priority: None,
+                title: milestone.title,
+                state: milestone.state,
+                description: milestone.description,
+                due_date: milestone.due_date,
             }
         }).collect())
     }
 
-    async fn fetch_workload_data(
-        client: &reqwest::Client, 
-        base_url: &str, 
-        token: &str, 
-        project_id: &str,
-        users: &[ProjectUser]
-    ) -> Result<WorkloadData> {
+    /// Group open issues and MRs by assignee. Items with several assignees count for each of them.
+    fn build_workload(issues: &[GitLabIssue], mrs: &[GitLabMR]) -> WorkloadData {
         let mut workload_data = WorkloadData::default();
         
-        println!("🔄 Fetching detailed workload data for {} users...", users.len());
-        
-        // Fetch issues and MRs for each user individually
-        for (i, user) in users.iter().enumerate() {
-            if i % 10 == 0 {
-                println!("   Processing user {}/{}: {}", i + 1, users.len(), user.username);
-            }
-            
-            let mut user_workload = UserWorkload {
-                username: user.username.clone(),
-                open_issues: Vec::new(),
-                open_mrs: Vec::new(),
-                issue_count: 0,
-                mr_count: 0,
-                total_score: 0,
-            };
-            
-            // Fetch user's open issues
-            if let Ok(issues) = Self::fetch_user_issues(client, base_url, token, project_id, &user.username).await {
-                user_workload.issue_count = issues.len();
-                user_workload.open_issues = issues;
-            }
-            
-            // Fetch user's open MRs
-            if let Ok(mrs) = Self::fetch_user_mrs(client, base_url, token, project_id, &user.username).await {
-                user_workload.mr_count = mrs.len();
-                user_workload.open_mrs = mrs;
+        for issue in issues {
+            let assignees = issue.assignee_usernames();
+            if assignees.is_empty() {
+                workload_data.unassigned_issues.push(HotIssue::from_issue(issue, None));
             }
-            
-            // Calculate total score (issues + 2*MRs)
-            user_workload.total_score = user_workload.issue_count + (user_workload.mr_count * 2);
-            
-            // Only store users with actual work
-            if user_workload.issue_count > 0 || user_workload.mr_count > 0 {
-                workload_data.user_assignments.insert(user.username.clone(), user_workload);
+            for username in assignees {
+                Self::user_workload(&mut workload_data, username)
+                    .open_issues
+                    .push(HotIssue::from_issue(issue, Some(username.to_string())));
             }
         }
         
-        // Fetch unassigned issues
-        if let Ok(unassigned) = Self::fetch_unassigned_issues(client, base_url, token, project_id).await {
-            workload_data.unassigned_issues = unassigned;
-        }
-        
-        // Calculate total open issues
-        let total_assigned: usize = workload_data.user_assignments.values().map(|w| w.issue_count).sum();
-        workload_data.total_open_issues = total_assigned + workload_data.unassigned_issues.len();
-        
-        println!("✅ Workload data complete: {} active users, {} total issues", 
-            workload_data.user_assignments.len(), workload_data.total_open_issues);
-        
-        Ok(workload_data)
-    }
-    
-    async fn fetch_user_issues(
-        client: &reqwest::Client, 
-        base_url: &str, 
-        token: &str, 
-        project_id: &str,
-        username: &str
-    ) -> Result<Vec<HotIssue>> {
-        let url = format!(
-            "{}/api/v4/projects/{}/issues?assignee_username={}&state=opened&per_page=100",
-            base_url, 
-            urlencoding::encode(project_id),
-            urlencoding::encode(username)
-        );
-        
-        let response = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", token)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            return Ok(Vec::new());
-        }
-        
-        let issues: Vec<serde_json::Value> = response.json().await?;
-        
-        Ok(issues.into_iter().map(|issue| {
-            let labels: Vec<String> = issue.get("labels")
-                .and_then(|l| l.as_array())
-                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
-                .unwrap_or_default();
-                
-            HotIssue {
-                id: issue.get("iid").and_then(|i| i.as_u64()).unwrap_or(0) as u32,
-                title: issue.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
-                assignee: Some(username.to_string()),
-                labels,
-                state: issue.get("state").and_then(|s| s.as_str()).unwrap_or("").to_string(),
-                updated_recently: true,
-                priority: None,
+        for mr in mrs {
+            for username in mr.assignee_usernames() {
+                Self::user_workload(&mut workload_data, username).open_mrs.push(MergeRequest {
+                    id: mr.iid as u32,
+                    title: mr.title.clone(),
+                    source_branch: mr.source_branch.clone(),
+                    target_branch: mr.target_branch.clone(),
+                    state: mr.state.clone(),
+                });
             }
-        }).collect())
-    }
-    
-    async fn fetch_user_mrs(
-        client: &reqwest::Client, 
-        base_url: &str, 
-        token: &str, 
-        project_id: &str,
-        username: &str
-    ) -> Result<Vec<MergeRequest>> {
-        let url = format!(
-            "{}/api/v4/projects/{}/merge_requests?assignee_username={}&state=opened&per_page=100",
-            base_url, 
-            urlencoding::encode(project_id),
-            urlencoding::encode(username)
-        );
-        
-        let response = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", token)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            return Ok(Vec::new());
         }
         
-        let mrs: Vec<serde_json::Value> = response.json().await?;
+        // Calculate total score (issues + 2*MRs)
+        for user_workload in workload_data.user_assignments.values_mut() {
+            user_workload.issue_count = user_workload.open_issues.len();
+            user_workload.mr_count = user_workload.open_mrs.len();
+            user_workload.total_score = user_workload.issue_count + (user_workload.mr_count * 2);
+        }
         
-        Ok(mrs.into_iter().map(|mr| {
-            MergeRequest {
-                id: mr.get("iid").and_then(|i| i.as_u64()).unwrap_or(0) as u32,
-                title: mr.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
-                source_branch: mr.get("source_branch").and_then(|s| s.as_str()).unwrap_or("").to_string(),
-                target_branch: mr.get("target_branch").and_then(|t| t.as_str()).unwrap_or("").to_string(),
-                state: mr.get("state").and_then(|s| s.as_str()).unwrap_or("").to_string(),
-            }
-        }).collect())
+        workload_data.total_open_issues = issues.len();
+        workload_data
     }
     
-    async fn fetch_unassigned_issues(
-        client: &reqwest::Client, 
-        base_url: &str, 
-        token: &str, 
-        project_id: &str
-    ) -> Result<Vec<HotIssue>> {
-        let url = format!(
-            "{}/api/v4/projects/{}/issues?state=opened&per_page=100",
-            base_url, 
-            urlencoding::encode(project_id)
-        );
-        
-        let response = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", token)
-            .send()
-            .await?;
-        
-        if !response.status().is_success() {
-            return Ok(Vec::new());
-        }
-        
-        let issues: Vec<serde_json::Value> = response.json().await?;
-        
-        // Filter to only unassigned issues
-        let unassigned: Vec<HotIssue> = issues.into_iter()
-            .filter_map(|issue| {
-                let has_assignee = issue.get("assignee").and_then(|a| a.as_object()).is_some() ||
-                    issue.get("assignees").and_then(|a| a.as_array()).map_or(false, |arr| !arr.is_empty());
-                
-                if !has_assignee {
-                    let labels: Vec<String> = issue.get("labels")
-                        .and_then(|l| l.as_array())
-                        .map(|arr| arr.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect())
-                        .unwrap_or_default();
-                        
-                    Some(HotIssue {
-                        id: issue.get("iid").and_then(|i| i.as_u64()).unwrap_or(0) as u32,
-                        title: issue.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string(),
-                        assignee: None,
-                        labels,
-                        state: issue.get("state").and_then(|s| s.as_str()).unwrap_or("").to_string(),
-                        updated_recently: true,
-                        priority: None,
-                    })
-                } else {
-                    None
-                }
+    fn user_workload<'a>(workload_data: &'a mut WorkloadData, username: &str) -> &'a mut UserWorkload {
+        workload_data.user_assignments
+            .entry(username.to_string())
+            .or_insert_with(|| UserWorkload {
+                username: username.to_string(),
+                ..UserWorkload::default()
             })
-            .collect();
-        
-        Ok(unassigned)
     }
 
 
     pub fn is_stale(&self) -> bool {
         // Consider context stale if older than 1 hour
-        if let Some(last_updated) = &self.last_updated {
-            if let Ok(updated_time) = chrono::DateTime::parse_from_rfc3339(last_updated) {
-                let now = chrono::Utc::now();
-                let duration = now.signed_duration_since(updated_time.with_timezone(&chrono::Utc));
-                return duration.num_hours() > 1;
-            }
+        if let Some(last_updated) = &self.last_updated
+            && let Ok(updated_time) = chrono::DateTime::parse_from_rfc3339(last_updated) {
+            let now = chrono::Utc::now();
+            let duration = now.signed_duration_since(updated_time.with_timezone(&chrono::Utc));
+            return duration.num_hours() > 1;
         }
         true // No update time means definitely stale
     }
@@ -590,4 +450,9 @@ impl ProjectContext {
         
         context
     }
-}
\ No newline at end of file
+}
+
+/// Replace characters that are not allowed in file names
+fn sanitize_file_name(name: &str) -> String {
+    name.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+}
@@ -0,0 +1,237 @@
+use anyhow::Result;
+use rig::completion::{Message, Prompt};
+use serde::{Deserialize, Serialize};
+use crate::agent::LlmAgent;
+
+/// Most recent turns kept word for word when older ones are summarized
+const KEEP_RECENT_TURNS: usize = 2;
+/// Average characters per token used by `estimate_tokens`
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// One question and the answer to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    pub user: String,
+    pub assistant: String,
+    /// When the answer arrived (RFC 3339)
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    /// Tools the agent called while answering
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// A tool call made by the agent, kept for the session record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub name: String,
+    pub arguments: String,
+    /// Start of the tool's output, or the error it returned
+    pub output: String,
+    #[serde(default)]
+    pub failed: bool,
+    /// Recorded under `--dry-run` instead of being run
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Rolling chat history for the REPL. Older turns are folded into `summary`
+/// once the history no longer fits the model's context window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Conversation {
+    pub summary: Option<String>,
+    pub turns: Vec<Turn>,
+}
+
+impl Conversation {
+    pub fn is_empty(&self) -> bool {
+        self.summary.is_none() && self.turns.is_empty()
+    }
+
+    pub fn push(&mut self, turn: Turn) {
+        self.turns.push(turn);
+    }
+
+    /// History in the form the agent expects, with the summary first
+    pub fn messages(&self) -> Vec<Message> {
+        let mut messages = Vec::with_capacity(self.turns.len() * 2 + 2);
+        if let Some(summary) = &self.summary {
+            messages.push(Message::user(format!("Summary of our earlier conversation:\n{}", summary)));
+            messages.push(Message::assistant("Understood, I'll keep that in mind."));
+        }
+        for turn in &self.turns {
+            messages.push(Message::user(turn.user.clone()));
+            messages.push(Message::assistant(turn.assistant.clone()));
+        }
+        messages
+    }
+
+    pub fn estimated_tokens(&self) -> usize {
+        self.summary.as_deref().map_or(0, estimate_tokens)
+            + self.turns.iter().map(|t| estimate_tokens(&t.user) + estimate_tokens(&t.assistant)).sum::<usize>()
+    }
+
+    /// Shrink the history to `budget` tokens: summarize all but the latest turns,
+    /// then drop the oldest turns if a few long answers still don't fit
+    pub async fn fit_within(&mut self, agent: &LlmAgent, budget: usize) {
+        if self.estimated_tokens() <= budget {
+            return;
+        }
+
+        if self.turns.len() > KEEP_RECENT_TURNS {
+            let older: Vec<Turn> = self.turns.drain(..self.turns.len() - KEEP_RECENT_TURNS).collect();
+            match self.summarize(agent, &older, budget).await {
+                Ok(summary) => {
+                    self.summary = Some(summary);
+                    println!("🗜️  Summarized {} earlier turns to stay within the model's context window", older.len());
+                }
+                Err(e) => println!("⚠️  Could not summarize earlier turns, dropping them: {}", e),
+            }
+        }
+
+        let mut dropped = 0;
+        while self.estimated_tokens() > budget && !self.turns.is_empty() {
+            self.turns.remove(0);
+            dropped += 1;
+        }
+        if self.estimated_tokens() > budget {
+            self.summary = None;
+        }
+        if dropped > 0 {
+            println!("🗜️  Dropped {} more turns to stay within the model's context window", dropped);
+        }
+    }
+
+    async fn summarize(&self, agent: &LlmAgent, turns: &[Turn], budget: usize) -> Result<String> {
+        let mut transcript = String::new();
+        if let Some(summary) = &self.summary {
+            transcript.push_str(&format!("Earlier summary: {}\n\n", summary));
+        }
+        for turn in turns {
+            transcript.push_str(&format!("User: {}\nKen: {}\n\n", turn.user, turn.assistant));
+        }
+
+        // Keep the newest part if even the transcript would overflow the model
+        let max_chars = budget.max(1000) * CHARS_PER_TOKEN;
+        if transcript.chars().count() > max_chars {
+            let skip = transcript.chars().count() - max_chars;
+            transcript = transcript.chars().skip(skip).collect();
+        }
+
+        let prompt = format!(
+            "Summarize this conversation between a user and Ken, a GitLab assistant, in one short paragraph. \
+             Keep issue and MR numbers, usernames, decisions and open questions. Do not call any tools.\n\n{}",
+            transcript
+        );
+        Ok(agent.prompt(prompt.as_str()).await?.trim().to_string())
+    }
+}
+
+impl Turn {
+    pub fn new(user: &str, assistant: &str, tool_calls: Vec<ToolCall>) -> Self {
+        Turn {
+            user: user.to_string(),
+            assistant: assistant.to_string(),
+            timestamp: Some(chrono::Utc::now().to_rfc3339()),
+            tool_calls,
+        }
+    }
+}
+
+/// Rough token count; close enough to decide when the history needs trimming
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count() / CHARS_PER_TOKEN + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentConfig, KenAgent};
+    use crate::config::LlmConfig;
+    use http_body_util::Full;
+    use hyper::body::{Bytes, Incoming};
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::Request;
+    use hyper_util::rt::TokioIo;
+    use std::convert::Infallible;
+    use tokio::net::TcpListener;
+
+    /// An agent using the OpenAI-style server at `url`
+    fn agent(url: &str) -> LlmAgent {
+        let llm = LlmConfig {
+            base_url: Some(format!("{}/v1", url)),
+            api_key: Some("key".to_string()),
+            model: Some("test".to_string()),
+            ..LlmConfig::default()
+        };
+        KenAgent::build(&AgentConfig::from_llm(&llm).unwrap())
+    }
+
+    /// Serve chat completions that all answer `reply` on a local port, returning its URL
+    async fn summarizer(reply: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service_fn(move |_: Request<Incoming>| async move {
+                    let body = serde_json::json!({
+                        "id": "c1", "object": "chat.completion", "created": 0, "model": "test",
+                        "choices": [{ "index": 0, "finish_reason": "stop", "message": { "role": "assistant", "content": reply } }],
+                        "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 },
+                    });
+                    let mut response = hyper::Response::new(Full::new(Bytes::from(body.to_string())));
+                    response.headers_mut().insert("content-type", "application/json".parse().unwrap());
+                    Ok::<_, Infallible>(response)
+                })));
+            }
+        });
+        url
+    }
+
+    /// Five turns of about 200 estimated tokens each
+    fn conversation() -> Conversation {
+        Conversation {
+            summary: None,
+            turns: (1..=5).map(|n| Turn::new(&format!("question {} {}", n, "q".repeat(400)), &"a".repeat(400), Vec::new())).collect(),
+        }
+    }
+
+    fn questions(conversation: &Conversation) -> Vec<String> {
+        conversation.turns.iter().map(|turn| turn.user.split(' ').take(2).collect::<Vec<_>>().join(" ")).collect()
+    }
+
+    #[tokio::test]
+    async fn leaves_history_that_fits() {
+        let mut conversation = conversation();
+        conversation.fit_within(&agent("http://127.0.0.1:9"), 2000).await;
+        assert_eq!(conversation.turns.len(), 5);
+        assert!(conversation.summary.is_none());
+    }
+
+    #[tokio::test]
+    async fn folds_older_turns_into_the_summary() {
+        let agent = agent(&summarizer("We triaged #12 with @alice.").await);
+        let mut conversation = conversation();
+        assert!(conversation.estimated_tokens() > 500);
+        
+        conversation.fit_within(&agent, 500).await;
+        assert_eq!(conversation.summary.as_deref(), Some("We triaged #12 with @alice."));
+        assert_eq!(questions(&conversation), ["question 4", "question 5"]);
+        assert!(conversation.estimated_tokens() <= 500);
+    }
+
+    #[tokio::test]
+    async fn drops_turns_when_summarizing_fails() {
+        // Nothing listens on a port the listener has just given up
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let agent = agent(&format!("http://{}", closed));
+        let mut conversation = conversation();
+        
+        conversation.fit_within(&agent, 250).await;
+        assert!(conversation.summary.is_none());
+        assert_eq!(questions(&conversation), ["question 5"]);
+        assert!(conversation.estimated_tokens() <= 250);
+    }
+}
@@ -0,0 +1,935 @@
+use reqwest::header::HeaderMap;
+use reqwest::{Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use futures::{StreamExt, TryStreamExt};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use crate::audit::{self, Action, Outcome, Source};
+use crate::config::Config;
+
+/// Page size requested from list endpoints (GitLab's maximum)
+pub const PER_PAGE: u32 = 100;
+/// Requests allowed in flight at once when `concurrency` is not configured
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// Retries after the first attempt when `max_retries` is not configured
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+/// Delay before the first retry; doubled on every further attempt
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// Longest we are willing to sleep before a single retry
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub type GitLabResult<T> = std::result::Result<T, GitLabError>;
+
+/// Errors returned by the GitLab REST API, split by the cases callers handle differently
+#[derive(Debug)]
+pub enum GitLabError {
+    /// 401: the token is missing, expired or revoked
+    Unauthorized,
+    /// 403: the token is valid but lacks access to the resource
+    Forbidden(String),
+    /// 404: the resource does not exist or is hidden from this user
+    NotFound(String),
+    /// 429: GitLab's rate limiter rejected the request
+    RateLimited { retry_after: Option<u64> },
+    /// 5xx: GitLab itself failed
+    Server(StatusCode),
+    /// Any other non-success status, with GitLab's error message if it sent one
+    Status(StatusCode, String),
+    /// The request never got a response (DNS, TLS, timeout, ...)
+    Request(reqwest::Error),
+    /// The response body did not match the expected shape
+    Decode(String),
+    /// A write held back under `--dry-run`; it was printed and audited but never sent
+    DryRun,
+}
+
+impl fmt::Display for GitLabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitLabError::Unauthorized => write!(f, "GitLab rejected the access token (401 Unauthorized)"),
+            GitLabError::Forbidden(path) => write!(f, "Access to {} is forbidden (403)", path),
+            GitLabError::NotFound(path) => write!(f, "{} was not found (404)", path),
+            GitLabError::RateLimited { retry_after: Some(secs) } => {
+                write!(f, "GitLab rate limit exceeded (429), retry after {}s", secs)
+            }
+            GitLabError::RateLimited { retry_after: None } => write!(f, "GitLab rate limit exceeded (429)"),
+            GitLabError::Server(status) => write!(f, "GitLab server error ({})", status),
+            GitLabError::Status(status, message) if message.is_empty() => write!(f, "GitLab returned {}", status),
+            GitLabError::Status(status, message) => write!(f, "GitLab returned {}: {}", status, message),
+            GitLabError::Request(e) => write!(f, "Request to GitLab failed: {}", e),
+            GitLabError::Decode(e) => write!(f, "Unexpected response from GitLab: {}", e),
+            GitLabError::DryRun => f.write_str("Dry run: nothing was sent to GitLab"),
+        }
+    }
+}
+
+impl std::error::Error for GitLabError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GitLabError::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl GitLabError {
+    /// Whether repeating the same request may succeed
+    fn is_transient(&self) -> bool {
+        match self {
+            GitLabError::RateLimited { .. } | GitLabError::Server(_) => true,
+            GitLabError::Request(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for GitLabError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            GitLabError::Decode(e.to_string())
+        } else {
+            GitLabError::Request(e)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct GitLabUser {
+    pub id: u64,
+    pub username: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectMember {
+    pub id: u64,
+    pub username: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+    pub access_level: u32,
+    /// Human readable form of `access_level`, filled in by the client
+    #[serde(default)]
+    pub role_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabProject {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub path_with_namespace: String,
+    #[serde(default)]
+    pub web_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabLabel {
+    pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabMilestone {
+    pub id: u64,
+    #[serde(default)]
+    pub iid: u64,
+    pub title: String,
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub due_date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabIssue {
+    pub id: u64,
+    pub iid: u64,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub state: String,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub assignee: Option<GitLabUser>,
+    #[serde(default)]
+    pub assignees: Vec<GitLabUser>,
+    #[serde(default)]
+    pub author: GitLabUser,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub milestone: Option<GitLabMilestone>,
+    #[serde(default)]
+    pub closed_at: Option<String>,
+    #[serde(default)]
+    pub closed_by: Option<GitLabUser>,
+    #[serde(default)]
+    pub web_url: String,
+}
+
+/// Fields for `POST /projects/:id/issues`
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct NewIssue {
+    pub title: String,
+    pub description: String,
+    /// Comma-separated, as the API expects
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub labels: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub milestone_id: Option<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub assignee_ids: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabMR {
+    pub id: u64,
+    pub iid: u64,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub state: String,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub assignee: Option<GitLabUser>,
+    #[serde(default)]
+    pub assignees: Vec<GitLabUser>,
+    #[serde(default)]
+    pub author: GitLabUser,
+    #[serde(default)]
+    pub source_branch: String,
+    #[serde(default)]
+    pub target_branch: String,
+    #[serde(default)]
+    pub reviewers: Vec<GitLabUser>,
+    #[serde(default)]
+    pub merged_at: Option<String>,
+    #[serde(default)]
+    pub merged_by: Option<GitLabUser>,
+    #[serde(default)]
+    pub web_url: String,
+    #[serde(default)]
+    pub merge_status: String,
+    #[serde(default)]
+    pub has_conflicts: bool,
+    #[serde(default)]
+    pub draft: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabNote {
+    pub id: u64,
+    pub body: String,
+    #[serde(default)]
+    pub author: GitLabUser,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub system: bool,
+    #[serde(default)]
+    pub resolvable: bool,
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+/// A discussion thread: a standalone comment (`individual_note`) or a comment with its replies
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabDiscussion {
+    pub id: String,
+    #[serde(default)]
+    pub individual_note: bool,
+    #[serde(default)]
+    pub notes: Vec<GitLabNote>,
+}
+
+/// An issue linked to another one, from `/issues/:iid/links`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IssueLink {
+    pub iid: u64,
+    pub title: String,
+    pub state: String,
+    /// `relates_to`, `blocks` or `is_blocked_by`, seen from the issue the links were listed for
+    #[serde(default)]
+    pub link_type: String,
+    #[serde(default)]
+    pub web_url: String,
+}
+
+/// An entry from the Events API: a comment, push, close, merge and so on
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitLabEvent {
+    pub id: u64,
+    #[serde(default)]
+    pub project_id: Option<u64>,
+    #[serde(default)]
+    pub author_id: Option<u64>,
+    #[serde(default)]
+    pub action_name: String,
+    #[serde(default)]
+    pub target_type: Option<String>,
+    #[serde(default)]
+    pub target_iid: Option<u64>,
+    #[serde(default)]
+    pub target_title: Option<String>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub push_data: Option<PushData>,
+    #[serde(default)]
+    pub note: Option<EventNote>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PushData {
+    #[serde(default)]
+    pub commit_count: u64,
+    #[serde(default)]
+    pub ref_type: Option<String>,
+    #[serde(default, rename = "ref")]
+    pub ref_name: Option<String>,
+    #[serde(default)]
+    pub commit_title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventNote {
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub noteable_type: Option<String>,
+    #[serde(default)]
+    pub noteable_iid: Option<u64>,
+}
+
+impl GitLabMR {
+    /// Usernames of everyone assigned, falling back to the legacy single `assignee` field
+    pub fn assignee_usernames(&self) -> Vec<&str> {
+        if !self.assignees.is_empty() {
+            self.assignees.iter().map(|u| u.username.as_str()).collect()
+        } else {
+            self.assignee.iter().map(|u| u.username.as_str()).collect()
+        }
+    }
+}
+
+impl GitLabIssue {
+    /// Usernames of everyone assigned, falling back to the legacy single `assignee` field
+    pub fn assignee_usernames(&self) -> Vec<&str> {
+        if !self.assignees.is_empty() {
+            self.assignees.iter().map(|u| u.username.as_str()).collect()
+        } else {
+            self.assignee.iter().map(|u| u.username.as_str()).collect()
+        }
+    }
+}
+
+/// Map a GitLab access level to its role name
+pub fn access_level_to_role(level: u32) -> String {
+    match level {
+        10 => "Guest".to_string(),
+        20 => "Reporter".to_string(),
+        30 => "Developer".to_string(),
+        40 => "Maintainer".to_string(),
+        50 => "Owner".to_string(),
+        _ => format!("Level {}", level),
+    }
+}
+
+/// Typed client for the GitLab REST API (v4)
+#[derive(Clone)]
+pub struct GitLabClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: String,
+    max_pages: Option<u32>,
+    max_retries: u32,
+    /// Shared by clones so the limit applies across everything one refresh runs
+    limiter: Arc<Semaphore>,
+    concurrency: usize,
+    /// Config profile the client was made for, recorded with each write in the audit log
+    profile: String,
+    /// Print and audit writes instead of sending them
+    dry_run: bool,
+}
+
+impl GitLabClient {
+    pub fn new(config: &Config) -> Self {
+        let concurrency = config.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+        Self {
+            http: reqwest::Client::new(),
+            base_url: config.gitlab_url.trim_end_matches('/').to_string(),
+            token: config.api_token.clone(),
+            max_pages: config.max_pages,
+            max_retries: config.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            limiter: Arc::new(Semaphore::new(concurrency)),
+            concurrency,
+            profile: config.profile.clone(),
+            dry_run: config.dry_run,
+        }
+    }
+    
+    /// Maximum number of requests this client runs in parallel
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub async fn current_user(&self) -> GitLabResult<GitLabUser> {
+        self.get("/user", &[]).await
+    }
+
+    /// Find a user by exact username
+    pub async fn find_user(&self, username: &str) -> GitLabResult<Option<GitLabUser>> {
+        let users: Vec<GitLabUser> = self.get("/users", &[("username", username)]).await?;
+        Ok(users.into_iter().next())
+    }
+
+    /// List projects visible to the user, limited to the first `limit` results
+    pub async fn list_projects(&self, limit: u32) -> GitLabResult<Vec<GitLabProject>> {
+        let per_page = limit.to_string();
+        self.get("/projects", &[("simple", "true"), ("per_page", &per_page)]).await
+    }
+
+    /// Events in a project by any author, newest first; filter with `after`, `before`, `action`, ...
+    pub async fn list_project_events(&self, project_id: &str, query: &[(&str, &str)]) -> GitLabResult<Vec<GitLabEvent>> {
+        self.get_all(&Self::project_path(project_id, "/events"), query).await
+    }
+
+    pub async fn list_issues(&self, project_id: &str, query: &[(&str, &str)]) -> GitLabResult<Vec<GitLabIssue>> {
+        self.get_all(&Self::project_path(project_id, "/issues"), query).await
+    }
+
+    pub async fn get_issue(&self, project_id: &str, iid: u64) -> GitLabResult<GitLabIssue> {
+        self.get(&Self::project_path(project_id, &format!("/issues/{}", iid)), &[]).await
+    }
+
+    /// The first `limit` issues matching `query` (at most one page), without paging through the rest
+    pub async fn list_recent_issues(&self, project_id: &str, query: &[(&str, &str)], limit: u32) -> GitLabResult<Vec<GitLabIssue>> {
+        let per_page = limit.min(PER_PAGE).to_string();
+        let mut query = query.to_vec();
+        query.push(("per_page", &per_page));
+        self.get(&Self::project_path(project_id, "/issues"), &query).await
+    }
+
+    pub async fn create_issue(&self, project_id: &str, issue: &NewIssue) -> GitLabResult<GitLabIssue> {
+        self.post(&Self::project_path(project_id, "/issues"), issue).await
+    }
+
+    /// Replace the assignees of an issue
+    pub async fn set_issue_assignees(&self, project_id: &str, iid: u64, assignee_ids: &[u64]) -> GitLabResult<GitLabIssue> {
+        let path = Self::project_path(project_id, &format!("/issues/{}", iid));
+        self.put(&path, &serde_json::json!({ "assignee_ids": assignee_ids })).await
+    }
+
+    pub async fn list_merge_requests(&self, project_id: &str, query: &[(&str, &str)]) -> GitLabResult<Vec<GitLabMR>> {
+        self.get_all(&Self::project_path(project_id, "/merge_requests"), query).await
+    }
+
+    /// List project members, including those inherited from parent groups
+    pub async fn list_members(&self, project_id: &str) -> GitLabResult<Vec<ProjectMember>> {
+        let mut members: Vec<ProjectMember> = self.get_all(&Self::project_path(project_id, "/members/all"), &[]).await?;
+        for member in &mut members {
+            member.role_name = access_level_to_role(member.access_level);
+        }
+        Ok(members)
+    }
+
+    pub async fn list_labels(&self, project_id: &str) -> GitLabResult<Vec<GitLabLabel>> {
+        self.get_all(&Self::project_path(project_id, "/labels"), &[]).await
+    }
+
+    pub async fn list_milestones(&self, project_id: &str) -> GitLabResult<Vec<GitLabMilestone>> {
+        self.get_all(&Self::project_path(project_id, "/milestones"), &[]).await
+    }
+
+    pub async fn list_issue_links(&self, project_id: &str, iid: u64) -> GitLabResult<Vec<IssueLink>> {
+        self.get(&Self::project_path(project_id, &format!("/issues/{}/links", iid)), &[]).await
+    }
+
+    /// All threads on an issue, oldest first, including system notes
+    pub async fn list_issue_discussions(&self, project_id: &str, iid: u64) -> GitLabResult<Vec<GitLabDiscussion>> {
+        self.get_all(&Self::project_path(project_id, &format!("/issues/{}/discussions", iid)), &[]).await
+    }
+
+    /// Relate two issues in the same project
+    pub async fn link_issues(&self, project_id: &str, iid: u64, target_iid: u64) -> GitLabResult<()> {
+        let path = Self::project_path(project_id, &format!("/issues/{}/links", iid));
+        let body = serde_json::json!({ "target_project_id": project_id, "target_issue_iid": target_iid });
+        self.post::<serde_json::Value, _>(&path, &body).await?;
+        Ok(())
+    }
+
+    /// Post a comment made of quick actions such as `/duplicate #12`.
+    /// GitLab answers these with a summary of the changes rather than a note, so nothing is returned.
+    pub async fn run_quick_actions(&self, project_id: &str, iid: u64, commands: &str) -> GitLabResult<()> {
+        let path = Self::project_path(project_id, &format!("/issues/{}/notes", iid));
+        self.post::<serde_json::Value, _>(&path, &serde_json::json!({ "body": commands })).await?;
+        Ok(())
+    }
+
+    pub async fn create_issue_note(&self, project_id: &str, iid: u64, body: &str) -> GitLabResult<GitLabNote> {
+        let path = Self::project_path(project_id, &format!("/issues/{}/notes", iid));
+        self.post(&path, &serde_json::json!({ "body": body })).await
+    }
+
+    /// Reply in an existing thread on an issue or merge request.
+    /// `noteable` is the API path segment, `issues` or `merge_requests`.
+    pub async fn reply_to_discussion(&self, project_id: &str, noteable: &str, iid: u64, discussion_id: &str, body: &str) -> GitLabResult<GitLabNote> {
+        let path = Self::project_path(project_id, &format!("/{}/{}/discussions/{}/notes", noteable, iid, discussion_id));
+        self.post(&path, &serde_json::json!({ "body": body })).await
+    }
+
+    fn project_path(project_id: &str, suffix: &str) -> String {
+        format!("/projects/{}{}", urlencoding::encode(project_id), suffix)
+    }
+
+    fn url(&self, path: &str, query: &[(&str, &str)]) -> GitLabResult<Url> {
+        let mut url = Url::parse(&format!("{}/api/v4{}", self.base_url, path))
+            .map_err(|e| GitLabError::Decode(format!("invalid URL for {}: {}", path, e)))?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        Ok(url)
+    }
+
+    /// Send a request, retrying rate-limited and transient failures with exponential backoff.
+    ///
+    /// GET requests are retried on any transient error. Other methods are only
+    /// retried on 429, where GitLab rejected the request before acting on it,
+    /// so a write is never applied twice.
+    async fn send(&self, request: reqwest::RequestBuilder) -> GitLabResult<Response> {
+        let request = request.header("PRIVATE-TOKEN", &self.token);
+        let idempotent = request.try_clone()
+            .and_then(|r| r.build().ok())
+            .is_some_and(|r| r.method() == reqwest::Method::GET);
+
+        let mut attempt = 0;
+        loop {
+            // Bodies we send are always buffered JSON, so cloning only fails on streams
+            let Some(current) = request.try_clone() else {
+                let _permit = self.limiter.acquire().await.expect("limiter is never closed");
+                let response = request.send().await?;
+                return Self::check(response).await;
+            };
+
+            let result = {
+                // Hold a permit only while the request is in flight, not while backing off
+                let _permit = self.limiter.acquire().await.expect("limiter is never closed");
+                match current.send().await {
+                    Ok(response) => Self::check(response).await,
+                    Err(e) => Err(e.into()),
+                }
+            };
+
+            let error = match result {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+
+            let retryable = match &error {
+                GitLabError::RateLimited { .. } => true,
+                e => idempotent && e.is_transient(),
+            };
+            if !retryable || attempt >= self.max_retries {
+                return Err(error);
+            }
+
+            let delay = match &error {
+                GitLabError::RateLimited { retry_after: Some(secs) } => Duration::from_secs(*secs),
+                _ => BASE_BACKOFF * 2u32.pow(attempt),
+            }.min(MAX_BACKOFF);
+
+            attempt += 1;
+            // On stderr, so it never ends up in machine-readable output such as `ken report --format json`
+            eprintln!("⏳ {}; retrying in {}s (attempt {}/{})",
+                error, delay.as_secs_f32().ceil(), attempt, self.max_retries);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Turn a non-success response into the matching `GitLabError`
+    async fn check(response: Response) -> GitLabResult<Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let path = response.url().path().to_string();
+        match status {
+            StatusCode::UNAUTHORIZED => Err(GitLabError::Unauthorized),
+            StatusCode::FORBIDDEN => Err(GitLabError::Forbidden(path)),
+            StatusCode::NOT_FOUND => Err(GitLabError::NotFound(path)),
+            StatusCode::TOO_MANY_REQUESTS => Err(GitLabError::RateLimited {
+                retry_after: retry_after_secs(response.headers(), chrono::Utc::now()),
+            }),
+            s if s.is_server_error() => Err(GitLabError::Server(s)),
+            s => {
+                // GitLab reports validation errors as {"message": ...} or {"error": ...}
+                let body: serde_json::Value = response.json().await.unwrap_or_default();
+                let message = body.get("message").or_else(|| body.get("error"))
+                    .map(|m| m.as_str().map(str::to_string).unwrap_or_else(|| m.to_string()))
+                    .unwrap_or_default();
+                Err(GitLabError::Status(s, message))
+            }
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> GitLabResult<T> {
+        let url = self.url(path, query)?;
+        let response = self.send(self.http.get(url)).await?;
+        Ok(response.json().await?)
+    }
+
+    async fn post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> GitLabResult<T> {
+        let url = self.url(path, &[])?;
+        let arguments = serde_json::to_value(body).unwrap_or_default();
+        self.hold_back_in_dry_run("POST", path, &arguments)?;
+        let response = self.send(self.http.post(url).json(body)).await;
+        self.audit("POST", path, arguments, Self::outcome(&response));
+        Ok(response?.json().await?)
+    }
+
+    async fn put<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> GitLabResult<T> {
+        let url = self.url(path, &[])?;
+        let arguments = serde_json::to_value(body).unwrap_or_default();
+        self.hold_back_in_dry_run("PUT", path, &arguments)?;
+        let response = self.send(self.http.put(url).json(body)).await;
+        self.audit("PUT", path, arguments, Self::outcome(&response));
+        Ok(response?.json().await?)
+    }
+
+    /// Under `--dry-run`, print and audit a write instead of sending it
+    fn hold_back_in_dry_run(&self, method: &str, path: &str, arguments: &serde_json::Value) -> GitLabResult<()> {
+        if !self.dry_run {
+            return Ok(());
+        }
+        println!("🧪 Dry run: would {} {}", method, path);
+        println!("{}", serde_json::to_string_pretty(arguments).unwrap_or_default());
+        self.audit(method, path, arguments.clone(), Outcome::DryRun);
+        Err(GitLabError::DryRun)
+    }
+
+    fn outcome(response: &GitLabResult<Response>) -> Outcome {
+        match response {
+            Ok(_) => Outcome::Ok,
+            Err(e) => Outcome::Failed(e.to_string()),
+        }
+    }
+
+    /// Record a write in the audit log, with the project taken from the path
+    fn audit(&self, method: &str, path: &str, arguments: serde_json::Value, outcome: Outcome) {
+        let project = path.strip_prefix("/projects/")
+            .and_then(|rest| rest.split('/').next())
+            .map(|id| urlencoding::decode(id).map_or_else(|_| id.to_string(), |id| id.into_owned()));
+        audit::record(&self.profile, project.as_deref(), Action {
+            source: Source::Api,
+            tool: &format!("{} {}", method, path),
+            arguments,
+            outcome,
+        });
+    }
+
+    /// Fetch every page of a list endpoint.
+    ///
+    /// When GitLab reports `X-Total-Pages` the remaining pages are fetched in
+    /// parallel (bounded by the client's concurrency). Otherwise it follows
+    /// `X-Next-Page` or the `Link: rel="next"` header one page at a time. The
+    /// configured `max_pages` caps the number of requests so a runaway listing
+    /// cannot hammer the server.
+    async fn get_all<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> GitLabResult<Vec<T>> {
+        let mut url = self.url(path, query)?;
+        if !query.iter().any(|(key, _)| *key == "per_page") {
+            url.query_pairs_mut().append_pair("per_page", &PER_PAGE.to_string());
+        }
+
+        let response = self.send(self.http.get(url.clone())).await?;
+        let headers = response.headers().clone();
+        let mut items: Vec<T> = response.json().await?;
+        let max_pages = self.max_pages.unwrap_or(u32::MAX);
+
+        let total_pages = headers.get("x-total-pages")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u32>().ok());
+
+        if let Some(total) = total_pages.filter(|_| headers.contains_key("x-next-page")) {
+            let last = total.min(max_pages);
+            if total > last {
+                // On stderr like the retry notices, so JSON output stays parseable
+                eprintln!("⚠️  Stopped after {} of {} pages of {} (max_pages); results are truncated", last, total, url.path());
+            }
+
+            let pages: Vec<Vec<T>> = futures::stream::iter(2..=last)
+                .map(|page| {
+                    let page_url = with_page(&url, &page.to_string());
+                    async move {
+                        let response = self.send(self.http.get(page_url)).await?;
+                        Ok::<_, GitLabError>(response.json::<Vec<T>>().await?)
+                    }
+                })
+                .buffered(self.concurrency)
+                .try_collect()
+                .await?;
+            items.extend(pages.into_iter().flatten());
+            return Ok(items);
+        }
+
+        let mut pages = 1;
+        let mut current = url;
+        let mut next = next_page_url(&current, &headers);
+
+        while let Some(page_url) = next.take() {
+            if pages >= max_pages {
+                eprintln!("⚠️  Stopped after {} pages of {} (max_pages); results are truncated", pages, current.path());
+                break;
+            }
+
+            let response = self.send(self.http.get(page_url.clone())).await?;
+            let headers = response.headers().clone();
+            let page: Vec<T> = response.json().await?;
+            items.extend(page);
+            pages += 1;
+
+            next = next_page_url(&page_url, &headers);
+            current = page_url;
+        }
+
+        Ok(items)
+    }
+}
+
+/// Seconds to wait before retrying, from `Retry-After` (seconds or an HTTP date)
+/// or GitLab's `RateLimit-Reset`
+fn retry_after_secs(headers: &HeaderMap, now: chrono::DateTime<chrono::Utc>) -> Option<u64> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+    let until = |time: i64| Some((time - now.timestamp()).max(1) as u64);
+
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<u64>() {
+            return Some(secs);
+        }
+        if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+            return until(date.timestamp());
+        }
+    }
+    // RateLimit-Reset is a Unix timestamp for when the quota refills
+    until(header("ratelimit-reset")?.parse().ok()?)
+}
+
+/// Copy of `url` requesting the given page number
+fn with_page(url: &Url, page: &str) -> Url {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != "page")
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    let mut url = url.clone();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("page", page);
+    url
+}
+
+/// Work out the URL of the next page from the pagination headers, if any
+fn next_page_url(current: &Url, headers: &HeaderMap) -> Option<Url> {
+    if let Some(page) = headers
+        .get("x-next-page")
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        return Some(with_page(current, page));
+    }
+
+    headers
+        .get_all("link")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|link| {
+            let (target, params) = link.split_once(';')?;
+            let is_next = params
+                .split(';')
+                .any(|p| matches!(p.trim(), "rel=\"next\"" | "rel=next"));
+            if !is_next {
+                return None;
+            }
+            let target = target.trim().trim_start_matches('<').trim_end_matches('>');
+            Url::parse(target).ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::Full;
+    use hyper::body::{Bytes, Incoming};
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::Request;
+    use hyper_util::rt::TokioIo;
+    use std::convert::Infallible;
+    use tokio::net::TcpListener;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs.iter().map(|(name, value)| (reqwest::header::HeaderName::from_static(name), value.parse().unwrap())).collect()
+    }
+
+    #[test]
+    fn next_page_from_x_next_page() {
+        let current = Url::parse("https://gitlab.example.com/api/v4/issues?state=opened&page=1").unwrap();
+        let next = next_page_url(&current, &headers(&[("x-next-page", "2")])).unwrap();
+        assert_eq!(next.as_str(), "https://gitlab.example.com/api/v4/issues?state=opened&page=2");
+        // GitLab sends an empty X-Next-Page on the last page
+        assert_eq!(next_page_url(&current, &headers(&[("x-next-page", "")])), None);
+        assert_eq!(next_page_url(&current, &HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn next_page_from_link_header() {
+        let current = Url::parse("https://gitlab.example.com/api/v4/issues").unwrap();
+        let link = "<https://gitlab.example.com/api/v4/issues?id_after=40&pagination=keyset>; rel=\"next\", \
+                    <https://gitlab.example.com/api/v4/issues?pagination=keyset>; rel=\"first\"";
+        let next = next_page_url(&current, &headers(&[("link", link)])).unwrap();
+        assert_eq!(next.as_str(), "https://gitlab.example.com/api/v4/issues?id_after=40&pagination=keyset");
+        
+        let last_page = "<https://gitlab.example.com/api/v4/issues?page=1>; rel=\"first\"";
+        assert_eq!(next_page_url(&current, &headers(&[("link", last_page)])), None);
+    }
+
+    /// Serve a list of three one-item pages on a local port. With `total`, pages carry
+    /// `X-Total-Pages` and `X-Next-Page`; otherwise only a `Link: rel="next"` header.
+    async fn paged_server(total: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let link_base = base.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let link_base = link_base.clone();
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service_fn(move |request: Request<Incoming>| {
+                    let page: u32 = Url::parse(&format!("http://x{}", request.uri())).unwrap()
+                        .query_pairs()
+                        .find(|(key, _)| key == "page")
+                        .map_or(1, |(_, page)| page.parse().unwrap());
+                    let mut response = hyper::Response::new(Full::new(Bytes::from(format!("[{}]", page))));
+                    let headers = response.headers_mut();
+                    headers.insert("content-type", "application/json".parse().unwrap());
+                    let next = if page < 3 { (page + 1).to_string() } else { String::new() };
+                    if total {
+                        headers.insert("x-total-pages", "3".parse().unwrap());
+                        headers.insert("x-next-page", next.parse().unwrap());
+                    } else if !next.is_empty() {
+                        let link = format!("<{}/api/v4/items?per_page=1&page={}>; rel=\"next\"", link_base, next);
+                        headers.insert("link", link.parse().unwrap());
+                    }
+                    async move { Ok::<_, Infallible>(response) }
+                })));
+            }
+        });
+        base
+    }
+
+    #[test]
+    fn reads_retry_after() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-10-17T07:28:00Z").unwrap().with_timezone(&chrono::Utc);
+        assert_eq!(retry_after_secs(&headers(&[("retry-after", "30")]), now), Some(30));
+        assert_eq!(retry_after_secs(&headers(&[("retry-after", "Sat, 17 Oct 2026 07:28:45 GMT")]), now), Some(45));
+        // A date already passed still waits a moment rather than not at all
+        assert_eq!(retry_after_secs(&headers(&[("retry-after", "Sat, 17 Oct 2026 07:00:00 GMT")]), now), Some(1));
+        let reset = (now.timestamp() + 20).to_string();
+        assert_eq!(retry_after_secs(&headers(&[("ratelimit-reset", &reset)]), now), Some(20));
+        assert_eq!(retry_after_secs(&headers(&[("retry-after", "7"), ("ratelimit-reset", &reset)]), now), Some(7));
+        assert_eq!(retry_after_secs(&headers(&[("retry-after", "soon")]), now), None);
+        assert_eq!(retry_after_secs(&HeaderMap::new(), now), None);
+    }
+
+    /// Answer every request with `status` (and `Retry-After: 0` on 429), counting the requests
+    async fn failing_server(status: u16) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = count.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let count = counted.clone();
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service_fn(move |_: Request<Incoming>| {
+                    count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let mut response = hyper::Response::new(Full::new(Bytes::from("{}")));
+                    *response.status_mut() = hyper::StatusCode::from_u16(status).unwrap();
+                    response.headers_mut().insert("retry-after", "0".parse().unwrap());
+                    async move { Ok::<_, Infallible>(response) }
+                })));
+            }
+        });
+        (url, count)
+    }
+
+    #[tokio::test]
+    async fn retries_writes_only_when_rate_limited() {
+        for (status, post_attempts, get_attempts) in [(429, 2, 2), (502, 1, 2)] {
+            let (url, count) = failing_server(status).await;
+            let mut config = Config::new(url, "token".to_string());
+            config.max_retries = Some(1);
+            let client = GitLabClient::new(&config);
+            
+            // Through `send`, so the failed write isn't audited into the real log
+            let request = client.http.post(client.url("/projects/1/issues", &[]).unwrap()).json(&serde_json::json!({}));
+            assert!(client.send(request).await.is_err());
+            assert_eq!(count.swap(0, std::sync::atomic::Ordering::SeqCst), post_attempts, "POST answered {}", status);
+            assert!(client.get::<serde_json::Value>("/projects/1", &[]).await.is_err());
+            assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), get_attempts, "GET answered {}", status);
+        }
+    }
+
+    fn client(url: String, max_pages: Option<u32>) -> GitLabClient {
+        let mut config = Config::new(url, "token".to_string());
+        config.max_pages = max_pages;
+        GitLabClient::new(&config)
+    }
+
+    #[tokio::test]
+    async fn fetches_every_page() {
+        for total in [true, false] {
+            let client = client(paged_server(total).await, None);
+            let items: Vec<u32> = client.get_all("/items", &[("per_page", "1")]).await.unwrap();
+            assert_eq!(items, [1, 2, 3], "with X-Total-Pages: {}", total);
+        }
+    }
+
+    #[tokio::test]
+    async fn stops_at_max_pages() {
+        for total in [true, false] {
+            let client = client(paged_server(total).await, Some(2));
+            let items: Vec<u32> = client.get_all("/items", &[("per_page", "1")]).await.unwrap();
+            assert_eq!(items, [1, 2], "with X-Total-Pages: {}", total);
+        }
+    }
+}
@@ -1,20 +1,35 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use rustyline::{Editor, Helper};
 use rustyline::completion::{Completer, Pair};
 use rustyline::hint::Hinter;
 use rustyline::highlight::Highlighter;
 use rustyline::validate::Validator;
 use rustyline::Context;
-use crate::config::Config;
-use crate::agent::KenAgent;
+use rustyline::error::ReadlineError;
+use rustyline::history::History;
+use crate::config::{Config, ConfigFile, DEFAULT_HISTORY_SIZE};
+use crate::credentials::looks_like_secret;
+use crate::agent::{AgentConfig, KenAgent, LlmAgent, Provider, ToolLog, ToolMode};
+use crate::conversation::{estimate_tokens, ToolCall, Turn};
+use crate::cli::NotConfigured;
 use crate::context::ProjectContext;
 use crate::mcp_client::MCPClient;
+use crate::prompts::{self, PromptKind, PromptSource};
+use crate::session::{self, ChatSession};
+use crate::issue_draft::{self, IssueDraft};
+use crate::assignee;
+use crate::activity::{self, DeveloperActivity};
+use crate::issue_summary::IssueThread;
+use crate::similarity::{self, DuplicateMatch};
+use crate::report::{Period, ProgressReport, ReportFormat};
+use crate::audit::{self, AuditFilter};
 use crate::gitlab_tools::GitLabTools;
-use rig::agent::Agent;
-use rig::providers::openai;
-use rig::completion::{Chat, Prompt};
+use crate::gitlab_client::{GitLabClient, GitLabError, GitLabIssue};
+use crate::util::truncate;
+use rig::completion::Prompt;
 use mcp_core::types::ToolsListResponse;
 use tokio::process::{Child, Command};
+use std::path::PathBuf;
 use std::time::Duration;
 
 #[derive(Clone)]
@@ -32,6 +47,14 @@ impl KenCompleter {
                 "/status".to_string(),
                 "/projects".to_string(),
                 "/project".to_string(),
+                "/profile".to_string(),
+                "/model".to_string(),
+                "/prompts".to_string(),
+                "/history".to_string(),
+                "/reset".to_string(),
+                "/sessions".to_string(),
+                "/resume".to_string(),
+                "/export".to_string(),
                 "/current".to_string(),
                 "/context".to_string(),
                 "/update-context".to_string(),
@@ -40,7 +63,13 @@ impl KenCompleter {
                 "/issues".to_string(),
                 "/mrs".to_string(),
                 "/create".to_string(),
+                "/suggest".to_string(),
+                "/duplicates".to_string(),
+                "/summary".to_string(),
+                "/summarize".to_string(),
                 "/workload".to_string(),
+                "/report".to_string(),
+                "/audit".to_string(),
                 "exit".to_string(),
                 "quit".to_string(),
             ],
@@ -123,54 +152,142 @@ impl Validator for KenCompleter {
 impl Helper for KenCompleter {}
 
 pub struct KenSession {
+    /// Profile this session reads and writes; `config` is `None` until it has been logged in
+    pub profile: String,
     pub config: Option<Config>,
     pub editor: Editor<KenCompleter, rustyline::history::DefaultHistory>,
-    pub agent: Option<Agent<openai::CompletionModel>>,
+    pub agent: Option<LlmAgent>,
+    /// Tokens available for history plus the current query (see `AgentConfig::history_budget`)
+    pub history_budget: usize,
+    /// The current conversation and its record under `~/.ken/sessions/`
+    pub chat: ChatSession,
+    /// REPL mode: answers stream to the terminal and `chat` is saved after each turn.
+    /// One-shot subcommands leave this off and print only the final answer.
+    pub interactive: bool,
+    /// Tool calls made by the agent during the current query
+    pub tool_log: ToolLog,
+    /// Whether the agent's mutating tool calls ask for approval or are only recorded
+    pub tool_mode: ToolMode,
     pub mcp_client: Option<MCPClient>,
     pub mcp_tools: Option<ToolsListResponse>,
     pub mcp_server_process: Option<Child>,
 }
 
 impl KenSession {
-    pub async fn new() -> Result<Self> {
+    pub async fn new(profile_override: Option<String>, project_override: Option<String>, dry_run: bool) -> Result<Self> {
+        let mut session = Self::without_mcp(profile_override, project_override, dry_run)?;
+        session.interactive = true;
+        if dry_run {
+            println!("🧪 Dry run: changes to GitLab are recorded but not made.");
+        }
+        
+        // Start MCP server immediately if we have config
+        if session.config.is_some() {
+            if let Err(e) = session.start_mcp_server().await {
+                println!("⚠️  GitLab MCP server failed to start: {}", e);
+                println!("    You can try restarting with /logout and /login");
+            }
+            session.rebuild_agent();
+        }
+        
+        Ok(session)
+    }
+    
+    /// Create a session without spawning the GitLab MCP server. Used by
+    /// subcommands that only talk to the GitLab REST API.
+    pub fn without_mcp(profile_override: Option<String>, project_override: Option<String>, dry_run: bool) -> Result<Self> {
         let mut editor = Editor::new().map_err(|e| anyhow::anyhow!("Failed to create editor: {}", e))?;
         
         // Set up autocomplete
         let completer = KenCompleter::new();
         editor.set_helper(Some(completer));
         
-        // Try to load existing config, but don't fail if it doesn't exist
-        let config = Config::load().ok();
+        // A --profile flag, like --project, applies to this run only
+        let explicit_profile = profile_override.is_some();
+        let profile = match profile_override {
+            Some(profile) => profile,
+            None => ConfigFile::load()?.active().to_string(),
+        };
         
-        let agent = if config.is_some() {
-            Some(KenAgent::default())
-        } else {
-            None
+        // Try to load existing config, but don't fail if it doesn't exist
+        let mut config = match Config::load_profile(Some(&profile)) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                if explicit_profile || Config::config_path()?.exists() {
+                    eprintln!("⚠️  {}", e);
+                }
+                None
+            }
         };
         
+        // A --project flag applies to this run only and is never saved
+        if let (Some(project), Some(config)) = (project_override, config.as_mut()) {
+            config.default_project_id = Some(project);
+        }
+        if let (true, Some(config)) = (dry_run, config.as_mut()) {
+            config.dry_run = true;
+        }
+        
+        let chat = ChatSession::new(&profile, config.as_ref().and_then(|c| c.default_project_id.as_deref()));
         let mut session = KenSession {
+            profile,
             config,
             editor,
-            agent,
+            agent: None,
+            history_budget: 0,
+            chat,
+            interactive: false,
+            tool_log: ToolLog::default(),
+            tool_mode: if dry_run { ToolMode::DryRun } else { ToolMode::Confirm },
             mcp_client: None,
             mcp_tools: None,
             mcp_server_process: None,
         };
-        
-        // Start MCP server immediately if we have config
-        if session.config.is_some() {
-            if let Err(e) = session.start_mcp_server().await {
-                println!("⚠️  GitLab MCP server failed to start: {}", e);
-                println!("    You can try restarting with /logout and /login");
+        session.rebuild_agent();
+        Ok(session)
+    }
+    
+    /// Rebuild the LLM agent, attaching MCP tools when the server is connected
+    pub fn rebuild_agent(&mut self) {
+        self.agent = None;
+        let Some(config) = &self.config else {
+            return;
+        };
+        let agent_config = match AgentConfig::from_llm(&config.llm) {
+            Ok(agent_config) => agent_config,
+            Err(e) => {
+                println!("⚠️  LLM agent unavailable: {}", e);
+                return;
             }
-        }
+        };
         
-        Ok(session)
+        self.history_budget = agent_config.history_budget();
+        self.agent = Some(match (&self.mcp_client, &self.mcp_tools) {
+            (Some(mcp_client), Some(tools)) => {
+                KenAgent::with_mcp_tools(&agent_config, &config.profile, config.default_project_id.as_deref(), mcp_client, tools.clone(), self.tool_log.clone(), self.tool_mode)
+            }
+            _ => KenAgent::build(&agent_config),
+        });
+    }
+    
+    /// Return the active config, or a `NotConfigured` error if not logged in
+    pub fn require_config(&self) -> Result<&Config> {
+        self.config.as_ref()
+            .ok_or_else(|| NotConfigured("Not authenticated. Use '/login' (or 'ken login') first.").into())
+    }
+    
+    /// Return the active config and its default project
+    pub fn require_project(&self) -> Result<(&Config, &str)> {
+        let config = self.require_config()?;
+        let project_id = config.default_project_id.as_deref()
+            .ok_or(NotConfigured("No default project set. Use '/project <id>' or pass --project."))?;
+        Ok((config, project_id))
     }
     
     pub async fn start_interactive(&mut self) -> Result<()> {
         // Show startup banner
         self.print_banner();
+        let mut history_path = self.load_history();
         
         loop {
             // Get user input
@@ -185,9 +302,18 @@ impl KenSession {
                         continue;
                     }
                     
-                    // Add to history
-                    self.editor.add_history_entry(trimmed)
-                        .map_err(|e| anyhow::anyhow!("Failed to add history: {}", e))?;
+                    // Add to history, keeping anything that looks like a pasted token out of it
+                    if looks_like_secret(trimmed) {
+                        println!("🔒 Not saved to history: the line looks like it contains a secret.");
+                    } else {
+                        self.editor.add_history_entry(trimmed)
+                            .map_err(|e| anyhow::anyhow!("Failed to add history: {}", e))?;
+                        if let Some(path) = &history_path
+                            && let Err(e) = self.editor.append_history(path) {
+                            println!("⚠️  Could not save command history to {}: {}", path.display(), e);
+                            history_path = None;
+                        }
+                    }
                     
                     // Handle exit commands
                     if matches!(trimmed.to_lowercase().as_str(), "exit" | "quit" | "/exit" | "/quit") {
@@ -196,18 +322,31 @@ impl KenSession {
                         break;
                     }
                     
-                    // Process the command
-                    if let Err(e) = self.process_input(trimmed).await {
-                        eprintln!("❌ Error: {}", e);
+                    audit::begin(trimmed, None);
+                    
+                    // Ctrl-C while a command runs cancels just that command;
+                    // at the prompt it still exits
+                    let outcome = tokio::select! {
+                        result = self.process_input(trimmed) => Some(result),
+                        _ = tokio::signal::ctrl_c() => None,
+                    };
+                    match outcome {
+                        Some(Ok(())) => {}
+                        // The held-back write has already been printed
+                        Some(Err(e)) if matches!(e.downcast_ref(), Some(GitLabError::DryRun)) => {
+                            println!("🧪 Nothing was changed.");
+                        }
+                        Some(Err(e)) => eprintln!("❌ Error: {}", e),
+                        None => self.query_cancelled(),
                     }
                 }
-                Err(rustyline::error::ReadlineError::Interrupted) => {
+                Err(ReadlineError::Interrupted) => {
                     // Ctrl-C
                     println!("👋 Goodbye!");
                     self.cleanup().await;
                     break;
                 }
-                Err(rustyline::error::ReadlineError::Eof) => {
+                Err(ReadlineError::Eof) => {
                     // Ctrl-D
                     println!("👋 Goodbye!");
                     self.cleanup().await;
@@ -223,9 +362,29 @@ impl KenSession {
         Ok(())
     }
     
+    /// Load `~/.ken/history` into the editor and return the file to keep appending to,
+    /// or `None` when persistent history is turned off
+    fn load_history(&mut self) -> Option<PathBuf> {
+        let size = ConfigFile::load().map(|file| file.history_size()).unwrap_or(DEFAULT_HISTORY_SIZE);
+        if size == 0 {
+            return None;
+        }
+        if let Err(e) = self.editor.history_mut().set_max_len(size) {
+            println!("⚠️  Invalid history_size {}: {}", size, e);
+        }
+        
+        let path = Config::config_path().ok()?.with_file_name("history");
+        match self.editor.load_history(&path) {
+            Ok(()) => {}
+            Err(ReadlineError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => println!("⚠️  Could not load command history from {}: {}", path.display(), e),
+        }
+        Some(path)
+    }
+    
     fn print_banner(&self) {
         if let Some(ref config) = self.config {
-            println!("✅ Authenticated to: {}", config.gitlab_url);
+            println!("✅ Authenticated to: {} (profile '{}')", config.gitlab_url, config.profile);
             if let Some(ref project) = config.default_project_id {
                 println!("📁 Current project: {}", project);
             } else {
@@ -254,6 +413,50 @@ impl KenSession {
             return self.handle_mrs_command(command).await;
         } else if command.starts_with("/project ") {
             return self.handle_project_command(command).await;
+        } else if command == "/profile" || command.starts_with("/profile ") {
+            return self.handle_profile_command(command).await;
+        } else if command == "/model" || command.starts_with("/model ") {
+            return self.handle_model_command(command);
+        } else if let Some(iid) = command.strip_prefix("/suggest ") {
+            let Ok(iid) = iid.trim().trim_start_matches('#').parse() else {
+                println!("❌ Usage: /suggest <issue iid>");
+                return Ok(());
+            };
+            return self.suggest_assignee(iid, false).await;
+        } else if command == "/report" || command.starts_with("/report ") {
+            return self.handle_report_command(&command["/report".len()..]).await;
+        } else if command == "/audit" || command.starts_with("/audit ") {
+            return audit::print(&AuditFilter::parse(&command["/audit".len()..])?);
+        } else if let Some(iid) = command.strip_prefix("/duplicates ") {
+            let Ok(iid) = iid.trim().trim_start_matches('#').parse() else {
+                println!("❌ Usage: /duplicates <issue iid>");
+                return Ok(());
+            };
+            return self.find_duplicates(iid).await;
+        } else if let Some(iid) = command.strip_prefix("/summarize ") {
+            let Ok(iid) = iid.trim().trim_start_matches('#').parse() else {
+                println!("❌ Usage: /summarize <issue iid>");
+                return Ok(());
+            };
+            return self.summarize_issue(iid, false).await;
+        } else if let Some(args) = command.strip_prefix("/summary ") {
+            let mut args = args.split_whitespace();
+            let username = args.next().unwrap_or_default();
+            let days = match args.next().map(str::parse::<i64>) {
+                None => activity::DEFAULT_DAYS,
+                Some(Ok(days)) if days > 0 => days,
+                Some(_) => {
+                    println!("❌ Usage: /summary @user [days]");
+                    return Ok(());
+                }
+            };
+            return self.summarize_developer(username, days).await;
+        } else if let Some(id) = command.strip_prefix("/resume ") {
+            return self.resume_session(id.trim());
+        } else if command == "/export" || command.starts_with("/export ") {
+            return self.export_session(command);
+        } else if let Some(profile) = command.strip_prefix("/login ") {
+            return self.login(Some(profile.trim())).await;
         }
         
         // Handle exact match commands
@@ -261,11 +464,20 @@ impl KenSession {
             "/help" => {
                 println!("📋 Available Commands:");
                 println!("  /help           - Show this help");
-                println!("  /login          - Login to GitLab");
-                println!("  /logout         - Logout and remove credentials");
+                println!("  /login [profile] - Login to GitLab (optionally into a named profile)");
+                println!("  /logout         - Logout and remove the current profile");
                 println!("  /status         - Check authentication status");
                 println!("  /projects       - List available projects");
                 println!("  /project <id>   - Set default project");
+                println!("  /profile [list] - List GitLab instance profiles");
+                println!("  /profile use <name> - Switch to another profile");
+                println!("  /model [[provider:]name] - Show LLM settings or switch model");
+                println!("  /prompts        - Show which prompt and template files are in effect");
+                println!("  /history        - Show the conversation so far");
+                println!("  /reset          - Forget the conversation and start a new session");
+                println!("  /sessions       - List saved conversations");
+                println!("  /resume <id>    - Continue a saved conversation");
+                println!("  /export [id] [file] - Write a conversation as a Markdown transcript");
                 println!("  /current        - Show current project");
                 println!("  /context        - View cached project context");
                 println!("  /update-context - Update project context from GitLab");
@@ -274,39 +486,34 @@ impl KenSession {
                 println!("  /issues [filter] - List project issues (optional: filter text)");
                 println!("  /mrs [filter]    - List merge requests (optional: filter text)");
                 println!("  /create         - Create new issue or merge request");
+                println!("  /duplicates <iid> - Find likely duplicates of an issue and link or close them");
+                println!("  /suggest <iid>  - Rank who should take an issue and optionally assign them");
+                println!("  /summarize <iid> - Summarize an issue and its discussion, optionally posting it as a comment");
+                println!("  /summary @user [days] - Summarize a developer's work and activity (default {} days)", activity::DEFAULT_DAYS);
+                println!("  /report [--daily|--weekly] [--format md|text|json] - Team progress digest");
+                println!("  /audit [--date|--since|--until YYYY-MM-DD] [--tool <glob>] [--project <id>] - Agent actions and GitLab writes");
                 println!("  /workload       - AI-enhanced team workload analysis with label weighting");
                 println!("  exit            - Quit Ken");
             }
             "/login" => {
-                println!("🔐 GitLab Authentication Setup");
-                let new_config = Config::prompt_for_login()?;
-                
-                println!("🔄 Verifying credentials...");
-                new_config.verify().await?;
-                
-                new_config.save()?;
-                self.config = Some(new_config);
-                
-                // Start MCP server and initialize integration after successful login
-                if let Err(e) = self.start_mcp_server().await {
-                    println!("⚠️  GitLab MCP server failed to start: {}", e);
-                }
-                
-                // Initialize agent with MCP tools if available
-                if let (Some(config), Some(mcp_client), Some(tools)) = (&self.config, &self.mcp_client, &self.mcp_tools) {
-                    self.agent = Some(KenAgent::with_mcp_tools(config, mcp_client, tools.clone()));
-                } else {
-                    self.agent = Some(KenAgent::default());
-                }
-                
-                println!("✅ Login successful!");
+                self.login(None).await?;
+            }
+            "/prompts" => {
+                self.show_prompts();
+            }
+            "/history" => {
+                self.show_history();
+            }
+            "/reset" => {
+                self.start_new_chat();
+                println!("🧹 Conversation history cleared.");
+            }
+            "/sessions" => {
+                self.list_sessions()?;
             }
             "/logout" => {
-                if self.config.is_some() {
-                    let config_path = Config::config_path()?;
-                    if config_path.exists() {
-                        std::fs::remove_file(config_path)?;
-                    }
+                if let Some(config) = &self.config {
+                    config.remove()?;
                     self.config = None;
                     self.agent = None;
                     self.mcp_client = None;
@@ -315,14 +522,14 @@ impl KenSession {
                     // Note: Keep MCP server running, just disconnect client
                     println!("🔌 Disconnected from GitLab MCP server");
                     
-                    println!("✅ Logged out successfully!");
+                    println!("✅ Logged out of profile '{}'", self.profile);
                 } else {
                     println!("❌ Not currently logged in.");
                 }
             }
             "/status" => {
                 if let Some(ref config) = self.config {
-                    println!("✅ Authenticated to: {}", config.gitlab_url);
+                    println!("✅ Authenticated to: {} (profile '{}')", config.gitlab_url, config.profile);
                     if let Some(ref project) = config.default_project_id {
                         println!("📁 Default project: {}", project);
                     }
@@ -356,62 +563,22 @@ impl KenSession {
                 }
             }
             "/context" => {
-                if let Some(ref config) = self.config {
-                    if let Some(ref project_id) = config.default_project_id {
-                        match ProjectContext::load(project_id) {
-                            Ok(context) => {
-                                println!("📋 Context for project: {}", project_id);
-                                println!("🕒 Last updated: {}", context.last_updated.as_deref().unwrap_or("Never"));
-                                println!("🏷️  Labels: {}", context.labels.len());
-                                println!("👥 Users: {}", context.users.len());
-                                println!("🎯 Milestones: {}", context.milestones.len());
-                                println!("🔥 Hot issues: {}", context.hot_issues.len());
-                            }
-                            Err(_) => {
-                                println!("❌ No cached context found. Use '/update-context' first.");
-                            }
-                        }
-                    } else {
-                        println!("❌ No default project set.");
-                    }
-                } else {
-                    println!("❌ Not authenticated. Use '/login' first.");
+                if let Err(e) = self.show_context() {
+                    println!("❌ {}", e);
                 }
             }
             "/update-context" => {
-                if let Some(ref config) = self.config {
-                    if let Some(ref project_id) = config.default_project_id {
-                        println!("🔄 Updating project context from GitLab...");
-                        match ProjectContext::fetch_from_gitlab(config, project_id).await {
-                            Ok(context) => {
-                                // Save the context to cache
-                                if let Err(e) = context.save() {
-                                    println!("⚠️  Context fetched but failed to save: {}", e);
-                                } else {
-                                    println!("✅ Project context updated and cached successfully!");
-                                }
-                                
-                                // Reinitialize agent with updated context
-                                if let (Some(config), Some(mcp_client), Some(tools)) = (&self.config, &self.mcp_client, &self.mcp_tools) {
-                                    self.agent = Some(KenAgent::with_mcp_tools(config, mcp_client, tools.clone()));
-                                } else {
-                                    self.agent = Some(KenAgent::default());
-                                }
-                            }
-                            Err(e) => {
-                                println!("❌ Failed to update context: {}", e);
-                            }
-                        }
-                    } else {
-                        println!("❌ No default project set. Use '/project <id>' first.");
-                    }
-                } else {
-                    println!("❌ Not authenticated. Use '/login' first.");
+                if let Err(e) = self.update_context().await {
+                    println!("❌ Failed to update context: {}", e);
                 }
             }
             "/list-tools" => {
                 if let Some(ref tools) = self.mcp_tools {
-                    println!("🔧 Available GitLab MCP Tools ({} total):", tools.tools.len());
+                    let profile = self.config.as_ref().and_then(|config| config.tools.profile.as_deref());
+                    match profile {
+                        Some(profile) => println!("🔧 Available GitLab MCP Tools ({} total, tool profile '{}'):", tools.tools.len(), profile),
+                        None => println!("🔧 Available GitLab MCP Tools ({} total):", tools.tools.len()),
+                    }
                     println!("─────────────────────────────────────");
                     
                     for (i, tool) in tools.tools.iter().enumerate() {
@@ -444,9 +611,7 @@ impl KenSession {
                             println!("✅ MCP server restarted successfully!");
                             
                             // Reinitialize agent with new MCP connection
-                            if let (Some(config), Some(mcp_client), Some(tools)) = (&self.config, &self.mcp_client, &self.mcp_tools) {
-                                self.agent = Some(KenAgent::with_mcp_tools(config, mcp_client, tools.clone()));
-                            }
+                            self.rebuild_agent();
                         }
                         Err(e) => {
                             println!("❌ Failed to restart MCP server: {}", e);
@@ -484,13 +649,8 @@ impl KenSession {
                 println!("📊 Analyzing team workload from context...");
                 
                 if let Some(ref config) = self.config {
-                    match self.analyze_workload_from_context(config).await {
-                        Ok(()) => {
-                            // Analysis completed and displayed
-                        }
-                        Err(e) => {
-                            println!("❌ Failed to analyze workload: {}", e);
-                        }
+                    if let Err(e) = self.analyze_workload_from_context(config).await {
+                        println!("❌ Failed to analyze workload: {}", e);
                     }
                 } else {
                     println!("❌ Not authenticated. Use '/login' first.");
@@ -503,78 +663,127 @@ impl KenSession {
         Ok(())
     }
     
-    async fn handle_query(&self, query: &str) -> Result<()> {
-        if let Some(ref agent) = self.agent {
-            if let Some(ref config) = self.config {
-                if let Some(ref project_id) = config.default_project_id {
-                    // Try to load context to enhance the query
-                    let context_info = match ProjectContext::load(project_id) {
-                        Ok(context) => context.to_prompt_context(),
-                        Err(_) => "No project context available. Use '/update-context' to fetch it.".to_string()
-                    };
-                    
-                    let enhanced_query = format!("Project Context:\n{}\n\nUser Query: {}", context_info, query);
-                    
-                    println!("🤖 Processing query...");
-                    match agent.chat(&enhanced_query, vec![]).await {
-                        Ok(response) => {
-                            println!("\n📝 Response:\n{}", response);
-                        }
-                        Err(e) => {
-                            println!("❌ Error processing query: {}", e);
-                        }
-                    }
-                } else {
-                    println!("❌ No project set. Use '/project <id>' to set a project first.");
-                }
-            } else {
-                println!("❌ Not authenticated. Use '/login' first.");
-            }
+    /// Ask for a GitLab URL and token, verify them and save them as `profile`.
+    /// Only the credentials: the one-shot `ken login` stops here, without starting the MCP server.
+    pub async fn save_login(profile: &str) -> Result<Config> {
+        println!("🔐 GitLab Authentication Setup");
+        println!("Profile: {}", profile);
+        let mut config = Config::prompt_for_login()?;
+        config.profile = profile.to_string();
+        config.keep_file_settings(&ConfigFile::load()?);
+        
+        println!("🔄 Verifying credentials...");
+        config.verify().await?;
+        
+        let store = config.store_token()?;
+        config.save()?;
+        println!("🔐 Token saved to {}", store);
+        Ok(config)
+    }
+    
+    /// Log in to `profile` (the current one when `None`) and switch the session to it.
+    /// If the login fails or is aborted, the session stays on the profile it had.
+    pub async fn login(&mut self, profile: Option<&str>) -> Result<()> {
+        let profile = profile.unwrap_or(&self.profile).to_string();
+        let mut new_config = Self::save_login(&profile).await?;
+        new_config.dry_run = self.tool_mode == ToolMode::DryRun;
+        self.config = Some(new_config);
+        if profile != self.profile {
+            self.profile = profile;
+            self.start_new_chat();
+        }
+        
+        // A running MCP server still holds the previous URL and token
+        if let Some(mut process) = self.mcp_server_process.take() {
+            let _ = process.kill().await;
+            self.mcp_client = None;
+            self.mcp_tools = None;
+        }
+        
+        // Start MCP server and initialize integration after successful login
+        if let Err(e) = self.start_mcp_server().await {
+            println!("⚠️  GitLab MCP server failed to start: {}", e);
+        }
+        
+        // Initialize agent with MCP tools if available
+        self.rebuild_agent();
+        
+        println!("✅ Login successful!");
+        Ok(())
+    }
+    
+    pub fn show_context(&self) -> Result<()> {
+        let (config, project_id) = self.require_project()?;
+        let context = ProjectContext::load(&config.profile, project_id)
+            .map_err(|_| anyhow::anyhow!("No cached context found. Use '/update-context' first."))?;
+        
+        println!("📋 Context for project: {}", project_id);
+        println!("🕒 Last updated: {}", context.last_updated.as_deref().unwrap_or("Never"));
+        println!("🏷️  Labels: {}", context.labels.len());
+        println!("👥 Users: {}", context.users.len());
+        println!("🎯 Milestones: {}", context.milestones.len());
+        println!("🔥 Hot issues: {}", context.hot_issues.len());
+        println!("📦 Recently closed issues: {}", context.recently_closed.len());
+        if !context.fetch_errors.is_empty() {
+            println!("⚠️  Incomplete: {} fetches failed during the last update", context.fetch_errors.len());
+        }
+        Ok(())
+    }
+    
+    pub async fn update_context(&mut self) -> Result<()> {
+        let (config, project_id) = self.require_project()?;
+        
+        println!("🔄 Updating project context from GitLab...");
+        let context = ProjectContext::fetch_from_gitlab(config, project_id).await?;
+        
+        // Save the context to cache
+        if let Err(e) = context.save(&config.profile) {
+            println!("⚠️  Context fetched but failed to save: {}", e);
+        } else if context.fetch_errors.is_empty() {
+            println!("✅ Project context updated and cached successfully!");
         } else {
-            println!("❌ LLM agent not initialized. Use '/login' to initialize.");
+            println!("⚠️  Project context cached, but {} fetches failed and their data is missing:", context.fetch_errors.len());
+            for error in &context.fetch_errors {
+                println!("   • {}", error);
+            }
+        }
+        
+        // Reinitialize agent with updated context
+        self.rebuild_agent();
+        Ok(())
+    }
+    
+    async fn handle_query(&mut self, query: &str) -> Result<()> {
+        println!("🤖 Processing query...");
+        // The answer has already been streamed to the terminal
+        if let Err(e) = self.query_with_context(query).await {
+            println!("❌ {}", e);
         }
         Ok(())
     }
 
-    async fn list_projects(&self, config: &Config) -> Result<()> {
+    pub async fn list_projects(&self, config: &Config) -> Result<()> {
         println!("📋 Fetching projects from GitLab...");
         
-        let url = format!("{}/api/v4/projects?simple=true&per_page=20", config.gitlab_url);
-        
-        let client = reqwest::Client::new();
-        let response = client
-            .get(&url)
-            .header("PRIVATE-TOKEN", &config.api_token)
-            .send()
-            .await?;
+        let projects = GitLabClient::new(config).list_projects(20)
+            .await
+            .context("Failed to fetch projects")?;
         
-        if response.status().is_success() {
-            let projects: Vec<serde_json::Value> = response.json().await?;
-            
-            if projects.is_empty() {
-                println!("No projects found.");
-            } else {
-                println!("\n📂 Available Projects:");
-                println!("─────────────────────");
-                for project in projects.iter() {
-                    let name = project.get("name").and_then(|n| n.as_str()).unwrap_or("Unknown");
-                    let id = project.get("id").and_then(|i| i.as_i64()).unwrap_or(0);
-                    let path_with_namespace = project.get("path_with_namespace")
-                        .and_then(|p| p.as_str())
-                        .unwrap_or("");
-                    
-                    println!("  • {} (ID: {}, Path: {})", name, id, path_with_namespace);
-                }
-                println!("\n💡 Use '/project <id_or_path>' to set a default project");
-            }
+        if projects.is_empty() {
+            println!("No projects found.");
         } else {
-            println!("❌ Failed to fetch projects: {}", response.status());
+            println!("\n📂 Available Projects:");
+            println!("─────────────────────");
+            for project in projects.iter() {
+                println!("  • {} (ID: {}, Path: {})", project.name, project.id, project.path_with_namespace);
+            }
+            println!("\n💡 Use '/project <id_or_path>' to set a default project");
         }
         
         Ok(())
     }
     
-    async fn start_mcp_server(&mut self) -> Result<()> {
+    pub async fn start_mcp_server(&mut self) -> Result<()> {
         let config = self.config.as_ref().ok_or_else(|| anyhow::anyhow!("No config available"))?;
         
         // If MCP server is already running, just try to reconnect
@@ -594,6 +803,25 @@ impl KenSession {
             .env("SSE", "true")
             .kill_on_drop(true);
         
+        // Keep the server out of ken's process group, so a Ctrl-C that cancels
+        // a query doesn't also reach (and kill) the server
+        #[cfg(unix)]
+        cmd.process_group(0);
+        // Outside the group a closed terminal no longer hangs the server up,
+        // so have the kernel stop it when ken exits. The signal follows the spawning
+        // thread, which is the main thread the REPL runs on.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        #[cfg(windows)]
+        cmd.creation_flags(0x0000_0200); // CREATE_NEW_PROCESS_GROUP
+        
         // Set project ID if available
         if let Some(ref project_id) = config.default_project_id {
             cmd.env("GITLAB_PROJECT_ID", project_id);
@@ -611,6 +839,7 @@ impl KenSession {
     async fn connect_to_mcp_server(&mut self) -> Result<()> {
         // Connect to the MCP server
         let mcp_server_url = "http://localhost:3002/sse";
+        let tools_config = self.config.as_ref().map(|config| config.tools.clone()).unwrap_or_default();
         println!("🔄 Connecting to GitLab MCP server at {}...", mcp_server_url);
         
         // Retry connection a few times as server might take time to start
@@ -623,7 +852,12 @@ impl KenSession {
                     // Get available tools
                     match client.get_tools_list().await {
                         Ok(tools) => {
-                            println!("📋 Loaded {} GitLab tools", tools.tools.len());
+                            let offered = tools.tools.len();
+                            let tools = tools_config.apply(tools)?;
+                            match &tools_config.profile {
+                                Some(profile) => println!("📋 Loaded {} of {} GitLab tools (tool profile '{}')", tools.tools.len(), offered, profile),
+                                None => println!("📋 Loaded {} GitLab tools", offered),
+                            }
                             self.mcp_tools = Some(tools);
                             self.mcp_client = Some(client);
                             return Ok(());
@@ -647,33 +881,166 @@ impl KenSession {
         Err(anyhow::anyhow!("Failed to connect to MCP server after multiple attempts"))
     }
     
-    async fn query_with_context(&self, query: &str) -> Result<String> {
-        if let Some(ref agent) = self.agent {
-            if let Some(ref config) = self.config {
-                if let Some(ref project_id) = config.default_project_id {
-                    // Try to load context to enhance the query
-                    let context_info = match ProjectContext::load(project_id) {
-                        Ok(context) => context.to_prompt_context(),
-                        Err(_) => "No project context available. Use '/update-context' to fetch it.".to_string()
-                    };
-                    
-                    let enhanced_query = format!(
-                        "Project Context:\n{}\n\nCurrent Project: {}\nGitLab API URL: {}\n\nUser Query: {}", 
-                        context_info, project_id, config.gitlab_url, query
-                    );
-                    
-                    match agent.chat(&enhanced_query, vec![]).await {
-                        Ok(response) => Ok(response),
-                        Err(e) => Err(anyhow::anyhow!("Error processing query: {}", e))
-                    }
-                } else {
-                    Err(anyhow::anyhow!("No project set. Use '/project <id>' to set a project first."))
-                }
-            } else {
-                Err(anyhow::anyhow!("Not authenticated. Use '/login' first."))
+    /// Ask the agent with the cached project context, continuing the conversation so far
+    pub async fn query_with_context(&mut self, query: &str) -> Result<String> {
+        let Some(agent) = self.agent.as_ref() else {
+            anyhow::bail!("LLM agent not initialized. Use '/login' to initialize.");
+        };
+        let Some(config) = self.config.as_ref() else {
+            anyhow::bail!("Not authenticated. Use '/login' first.");
+        };
+        let Some(project_id) = config.default_project_id.as_ref() else {
+            anyhow::bail!("No project set. Use '/project <id>' to set a project first.");
+        };
+        
+        // Try to load context to enhance the query
+        let context_info = match ProjectContext::load(&config.profile, project_id) {
+            Ok(context) => context.to_prompt_context(),
+            Err(_) => "No project context available. Use '/update-context' to fetch it.".to_string()
+        };
+        
+        let enhanced_query = format!(
+            "Project Context:\n{}\n\nCurrent Project: {}\nGitLab API URL: {}\n\nUser Query: {}", 
+            context_info, project_id, config.gitlab_url, query
+        );
+        
+        // The project context is resent with every query, so history only keeps what was asked
+        let budget = self.history_budget.saturating_sub(estimate_tokens(&enhanced_query));
+        self.chat.conversation.fit_within(agent, budget).await;
+        
+        if let Ok(mut log) = self.tool_log.lock() {
+            log.clear();
+        }
+        let response = KenAgent::stream_chat(agent, &enhanced_query, self.chat.conversation.messages(), self.interactive)
+            .await
+            .map_err(|e| anyhow::anyhow!("Error processing query: {}", e))?;
+        let tool_calls = self.tool_log.lock().map(|mut log| std::mem::take(&mut *log)).unwrap_or_default();
+        if self.interactive {
+            print_dry_run(&tool_calls);
+        }
+        
+        let project_id = project_id.clone();
+        self.chat.record(&project_id, Turn::new(query, &response, tool_calls));
+        if self.interactive && let Err(e) = self.chat.save() {
+            println!("⚠️  Could not save session: {}", e);
+        }
+        Ok(response)
+    }
+    
+    /// Clean up after Ctrl-C dropped a running command. The turn is only recorded once the
+    /// answer is complete, so the conversation is as it was before the question.
+    fn query_cancelled(&mut self) {
+        if let Ok(mut log) = self.tool_log.lock() {
+            log.clear();
+        }
+        println!("\n⏹️  Cancelled. Press Ctrl-C again at the prompt to quit.");
+        
+        // The server runs in its own process group, so it should have survived
+        if let Some(process) = self.mcp_server_process.as_mut()
+            && !matches!(process.try_wait(), Ok(None)) {
+            println!("⚠️  The GitLab MCP server has stopped. Use /restart-mcp to start it again.");
+            self.mcp_server_process = None;
+        }
+    }
+    
+    /// Start an empty conversation; the previous one stays on disk
+    fn start_new_chat(&mut self) {
+        let project_id = self.config.as_ref().and_then(|c| c.default_project_id.as_deref());
+        self.chat = ChatSession::new(&self.profile, project_id);
+    }
+    
+    fn list_sessions(&self) -> Result<()> {
+        let sessions = ChatSession::list()?;
+        if sessions.is_empty() {
+            println!("💬 No saved sessions yet.");
+            return Ok(());
+        }
+        
+        println!("💬 Saved sessions (newest first):");
+        for saved in &sessions {
+            let marker = if saved.id == self.chat.id { "*" } else { " " };
+            println!("{} {}  {}  {}/{}  {} turns  {}",
+                marker,
+                saved.id,
+                session::display_time(&saved.updated_at),
+                saved.profile,
+                saved.project_id.as_deref().unwrap_or("-"),
+                saved.turns.len(),
+                truncate(saved.title(), 60),
+            );
+        }
+        println!("\n💡 Use '/resume <id>' to continue one or '/export <id>' to get a transcript");
+        Ok(())
+    }
+    
+    /// Continue a saved conversation, switching to its project for this run
+    fn resume_session(&mut self, id: &str) -> Result<()> {
+        let saved = ChatSession::load(id)?;
+        if saved.profile != self.profile {
+            println!("⚠️  Session '{}' was recorded with profile '{}'; you are using '{}'.", saved.id, saved.profile, self.profile);
+            println!("    Use '/profile use {}' first if it refers to another GitLab instance.", saved.profile);
+        }
+        
+        if let (Some(project), Some(config)) = (&saved.project_id, self.config.as_mut())
+            && config.default_project_id.as_ref() != Some(project) {
+            config.default_project_id = Some(project.clone());
+            println!("📁 Switched to project {} for this session", project);
+            self.rebuild_agent();
+        }
+        
+        println!("✅ Resumed session {} ({} turns, last updated {})", saved.id, saved.turns.len(), session::display_time(&saved.updated_at));
+        if let Some(last) = saved.turns.last() {
+            println!("    Last question: {}", truncate(&last.user, 100));
+        }
+        self.chat = saved;
+        Ok(())
+    }
+    
+    fn export_session(&self, command: &str) -> Result<()> {
+        let args: Vec<&str> = command.split_whitespace().skip(1).collect();
+        let (saved, path) = match args.as_slice() {
+            [] => (self.chat.clone(), None),
+            [id] => (self.load_chat(id)?, None),
+            [id, path] => (self.load_chat(id)?, Some(*path)),
+            _ => {
+                println!("❌ Usage: /export [id] [file]");
+                return Ok(());
             }
+        };
+        if saved.is_empty() {
+            println!("💬 Nothing to export yet.");
+            return Ok(());
+        }
+        
+        let path = path.map(str::to_string).unwrap_or_else(|| format!("ken-session-{}.md", saved.id));
+        std::fs::write(&path, saved.to_markdown())
+            .with_context(|| format!("Failed to write {}", path))?;
+        println!("📄 Exported session {} ({} turns) to {}", saved.id, saved.turns.len(), path);
+        Ok(())
+    }
+    
+    /// The current conversation if `id` names it, otherwise a saved one
+    fn load_chat(&self, id: &str) -> Result<ChatSession> {
+        if id == self.chat.id {
+            Ok(self.chat.clone())
         } else {
-            Err(anyhow::anyhow!("LLM agent not initialized. Use '/login' to initialize."))
+            ChatSession::load(id)
+        }
+    }
+
+    fn show_history(&self) {
+        if self.chat.conversation.is_empty() {
+            println!("💬 No conversation yet.");
+            return;
+        }
+        
+        println!("💬 Conversation {} ({} turns, ~{} tokens):", self.chat.id, self.chat.conversation.turns.len(), self.chat.conversation.estimated_tokens());
+        if let Some(summary) = &self.chat.conversation.summary {
+            println!("\n📜 Earlier turns (summarized):\n{}", summary);
+        }
+        for (i, turn) in self.chat.conversation.turns.iter().enumerate() {
+            println!("\n[{}] 👤 {}", i + 1, truncate(&turn.user, 200));
+            println!("    🤖 {}", truncate(&turn.assistant, 300).replace('\n', "\n       "));
         }
     }
 
@@ -687,13 +1054,8 @@ impl KenSession {
             "List the current open issues in this project".to_string()
         };
         
-        match self.query_with_context(&query).await {
-            Ok(response) => {
-                println!("\n{}", response);
-            }
-            Err(e) => {
-                println!("❌ {}", e);
-            }
+        if let Err(e) = self.query_with_context(&query).await {
+            println!("❌ {}", e);
         }
         Ok(())
     }
@@ -708,14 +1070,93 @@ impl KenSession {
             "List the current open merge requests in this project".to_string()
         };
         
-        match self.query_with_context(&query).await {
-            Ok(response) => {
-                println!("\n{}", response);
+        if let Err(e) = self.query_with_context(&query).await {
+            println!("❌ {}", e);
+        }
+        Ok(())
+    }
+    
+    async fn handle_profile_command(&mut self, command: &str) -> Result<()> {
+        let args: Vec<&str> = command.split_whitespace().skip(1).collect();
+        match args.as_slice() {
+            [] | ["list"] => self.list_profiles(),
+            ["use", name] => self.use_profile(name).await,
+            _ => {
+                println!("❌ Usage: /profile [list] | /profile use <name>");
+                Ok(())
             }
-            Err(e) => {
-                println!("❌ {}", e);
+        }
+    }
+    
+    pub fn list_profiles(&self) -> Result<()> {
+        let file = ConfigFile::load()?;
+        if file.profiles.is_empty() {
+            println!("❌ No profiles configured. Use '/login' to create one.");
+            return Ok(());
+        }
+        
+        println!("👤 Profiles:");
+        for (name, config) in &file.profiles {
+            let marker = if *name == self.profile { "*" } else { " " };
+            let default = if name == file.active() { " (default)" } else { "" };
+            println!("{} {} - {}{}", marker, name, config.gitlab_url, default);
+        }
+        Ok(())
+    }
+    
+    /// Switch this session to another profile and make it the default for future runs
+    pub async fn use_profile(&mut self, name: &str) -> Result<()> {
+        let mut config = Config::load_profile(Some(name))?;
+        config.set_active()?;
+        config.dry_run = self.tool_mode == ToolMode::DryRun;
+        
+        println!("✅ Switched to profile '{}' ({})", name, config.gitlab_url);
+        self.profile = name.to_string();
+        self.config = Some(config);
+        self.start_new_chat();
+        
+        // The MCP server holds the previous instance's URL and token
+        if let Some(mut process) = self.mcp_server_process.take() {
+            let _ = process.kill().await;
+            self.mcp_client = None;
+            self.mcp_tools = None;
+            if let Err(e) = self.start_mcp_server().await {
+                println!("⚠️  GitLab MCP server failed to start: {}", e);
             }
         }
+        self.rebuild_agent();
+        Ok(())
+    }
+    
+    fn handle_model_command(&mut self, command: &str) -> Result<()> {
+        let model = command[6..].trim(); // Remove "/model"
+        let Some(config) = self.config.as_mut() else {
+            println!("❌ Not authenticated. Use '/login' first.");
+            return Ok(());
+        };
+        
+        if model.is_empty() {
+            let llm = AgentConfig::from_llm(&config.llm)?;
+            println!("🤖 LLM settings:");
+            println!("  Provider:    {}", llm.provider);
+            println!("  Model:       {}", llm.model_name);
+            println!("  Endpoint:    {}", llm.base_url);
+            println!("  API key:     {}", if llm.api_key.is_empty() { "not set" } else { "set" });
+            println!("  Temperature: {}", llm.temperature);
+            println!("  Max tokens:  {}", llm.max_tokens);
+            return Ok(());
+        }
+        
+        // `provider:model` also switches backend; a plain name may itself contain ':' (e.g. Ollama tags)
+        let (provider, model) = match model.split_once(':') {
+            Some((provider, name)) if provider.parse::<Provider>().is_ok() => (Some(provider), name),
+            _ => (None, model),
+        };
+        
+        config.set_model(provider, model)?;
+        let llm = AgentConfig::from_llm(&config.llm)?;
+        self.rebuild_agent();
+        println!("✅ Model set to: {} ({} at {})", llm.model_name, llm.provider, llm.base_url);
         Ok(())
     }
     
@@ -733,103 +1174,420 @@ impl KenSession {
         Ok(())
     }
     
-    fn get_issue_template() -> String {
-        r#"## 背景
-
-このissueが切られた経緯や背景情報を記入してください
-
-## 作業項目
-
-1. [ ] 実際に作業する内容を（可能であれば順番に）列挙してください
-
-## 完了条件
-
-* [ ] どのような状態になっていれば完了としてよいかの条件を列挙してください"#.to_string()
+    pub fn get_issue_template() -> String {
+        prompts::load(PromptKind::IssueTemplate).text
     }
     
-    fn get_mr_template() -> String {
-        r#"## 概要
-（何を目的としたどんな変更か）
-
-## 検証項目
-（このMRの変更に対する検証の内容について）
-
-## 重点レビュー箇所
-（特にレビュワーに見てほしいものがあればリスト形式で記載。特になくてもいい）
-
-## 関連Issue
-tasks#"#.to_string()
+    fn show_prompts(&self) {
+        println!("📝 Prompts in effect:");
+        for kind in PromptKind::ALL {
+            println!("  {:<15} {}", kind.description(), prompts::load(kind).source);
+        }
+        
+        println!("\n💡 Override with .ken/<file> in your project or ~/.ken/prompts/<file>:");
+        for kind in PromptKind::ALL {
+            println!("   - {}", kind.file_name());
+        }
+        if let Some(dir) = prompts::user_prompt_dir() {
+            println!("   User prompt directory: {}", dir.display());
+        }
+        println!("   Changes to the system prompt apply after /restart-mcp or /model.");
+    }
+    
+    /// With a custom template the guided questions no longer match its sections,
+    /// so ask for free-form details and let the agent fill the template in
+    async fn create_from_custom_template(&mut self, kind: &str, title: &str, template: &str, extra: &str) {
+        println!("\n📄 Using your {} template:\n{}\n", kind, template);
+        println!("Describe the {} (the agent will fill in the template):", kind);
+        let details = self.editor.readline("> ").unwrap_or_default();
+        
+        println!("\n🔄 Creating {} with your template...", kind);
+        let query = format!(
+            "Create a new GitLab {} with title: '{}'{}. Write the description by filling in this template:\n{}\n\nDetails: {}",
+            kind, title, extra, template, details.trim()
+        );
+        
+        match self.query_with_context(&query).await {
+            Ok(response) => {
+                println!("\n✅ {}", response);
+            }
+            Err(e) => {
+                println!("❌ {}", e);
+            }
+        }
     }
     
     async fn create_issue_with_template(&mut self) {
-        println!("📝 Creating Issue with Template");
+        println!("📝 Creating Issue");
         println!("─────────────────────────────────");
-        
-        // Get issue title
-        let title = match self.editor.readline("Issue Title: ") {
-            Ok(t) if !t.trim().is_empty() => t.trim().to_string(),
+        println!("Describe the problem or task in your own words:");
+        let report = match self.editor.readline("> ") {
+            Ok(report) if !report.trim().is_empty() => report,
             _ => {
-                println!("❌ Issue title cannot be empty.");
+                println!("❌ Issue description cannot be empty.");
                 return;
             }
         };
         
-        // Get background
-        println!("\n💡 背景 (Background - why this issue is being created):");
-        let background = match self.editor.readline("> ") {
-            Ok(b) if !b.trim().is_empty() => b.trim().to_string(),
-            _ => "詳細は後ほど記載".to_string()
-        };
+        if let Err(e) = self.create_issue_from_description(&report, false).await {
+            println!("❌ {}", e);
+        }
+    }
+    
+    /// Draft an issue from a free-form report, show it for review and create it once accepted.
+    /// With `assume_yes` the draft is created without asking.
+    pub async fn create_issue_from_description(&mut self, report: &str, assume_yes: bool) -> Result<()> {
+        let (config, project_id) = self.require_project()?;
+        let (config, project_id) = (config.clone(), project_id.to_string());
         
-        // Get work items
-        println!("\n📋 作業項目 (Work items - enter items separated by comma):");
-        println!("   Example: APIの実装, テストの追加, ドキュメントの更新");
-        let work_items_input = self.editor.readline("> ").unwrap_or_default();
-        let work_items: Vec<String> = if !work_items_input.trim().is_empty() {
-            work_items_input.split(',')
-                .map(|s| format!("[ ] {}", s.trim()))
-                .collect()
-        } else {
-            vec!["[ ] 作業項目を追加してください".to_string()]
+        // A tool-less agent, so drafting can never create anything by itself
+        let agent = KenAgent::build(&AgentConfig::from_llm(&config.llm)?);
+        let context = ProjectContext::cached(&config, &project_id).await?;
+        
+        println!("🤖 Drafting issue...");
+        let template = Self::get_issue_template();
+        let mut draft = IssueDraft::generate(&agent, report, &template, &context).await?;
+        let mut notes = draft.validate(&context);
+        let mut duplicates = Vec::new();
+        let mut show_draft = true;
+        
+        loop {
+            if show_draft {
+                println!("\n{}", draft.preview());
+                for note in &notes {
+                    println!("⚠️  {}", note);
+                }
+                duplicates = similarity::find_duplicates(&context, &format!("{}\n{}", draft.title, draft.description), None);
+                Self::print_duplicates(&duplicates);
+                show_draft = false;
+            }
+            if assume_yes {
+                break;
+            }
+            
+            match self.editor.readline("Create this issue? [a]ccept / [e]dit / [r]eject: ") {
+                Ok(answer) => match answer.trim().to_lowercase().as_str() {
+                    "a" | "accept" | "y" | "yes" => break,
+                    "e" | "edit" => {
+                        self.edit_draft(&mut draft);
+                        notes = draft.validate(&context);
+                        show_draft = true;
+                    }
+                    "r" | "reject" | "n" | "no" => {
+                        println!("🗑️  Draft discarded; nothing was created.");
+                        return Ok(());
+                    }
+                    _ => println!("❌ Please answer a, e or r."),
+                },
+                Err(ReadlineError::Eof) => {
+                    anyhow::bail!("No answer on stdin; nothing was created. Pass --yes to create the draft without review.");
+                }
+                Err(_) => {
+                    println!("🗑️  Draft discarded; nothing was created.");
+                    return Ok(());
+                }
+            }
+        }
+        
+        println!("🔄 Creating issue...");
+        let client = GitLabClient::new(&config);
+        let issue = draft.create(&client, &project_id).await?;
+        println!("✅ Created issue #{}: {}", issue.iid, issue.title);
+        if !issue.web_url.is_empty() {
+            println!("🔗 {}", issue.web_url);
+        }
+        if !duplicates.is_empty() && !assume_yes {
+            self.resolve_duplicates(&client, &project_id, issue.iid, &duplicates).await?;
+        }
+        Ok(())
+    }
+    
+    /// List likely duplicates of an existing issue and offer to link them or close it as a duplicate
+    pub async fn find_duplicates(&mut self, iid: u64) -> Result<()> {
+        let (config, project_id) = self.require_project()?;
+        let (config, project_id) = (config.clone(), project_id.to_string());
+        let client = GitLabClient::new(&config);
+        
+        let context = ProjectContext::cached(&config, &project_id).await?;
+        let issue = client.get_issue(&project_id, iid).await?;
+        let text = format!("{}\n{}", issue.title, issue.description.as_deref().unwrap_or(""));
+        let duplicates = similarity::find_duplicates(&context, &text, Some(iid));
+        
+        if duplicates.is_empty() {
+            println!("✅ No likely duplicates of #{} among {} open and {} recently closed issues.",
+                iid, context.hot_issues.len(), context.recently_closed.len());
+            return Ok(());
+        }
+        println!("📌 #{}: {}", issue.iid, issue.title);
+        Self::print_duplicates(&duplicates);
+        self.resolve_duplicates(&client, &project_id, iid, &duplicates).await
+    }
+    
+    fn print_duplicates(duplicates: &[DuplicateMatch]) {
+        if duplicates.is_empty() {
+            return;
+        }
+        println!("🔁 Possible duplicates:");
+        for (i, duplicate) in duplicates.iter().enumerate() {
+            println!("  {}. #{:<5} {:>3.0}%  {} ({})", i + 1, duplicate.iid, duplicate.score * 100.0, duplicate.title, duplicate.state);
+        }
+    }
+    
+    /// Offer to relate `iid` to one of its duplicates or close it as a duplicate of one
+    async fn resolve_duplicates(&mut self, client: &GitLabClient, project_id: &str, iid: u64, duplicates: &[DuplicateMatch]) -> Result<()> {
+        let question = format!("[l]ink #{} to a duplicate / [c]lose #{} as a duplicate / [n]othing: ", iid, iid);
+        let answer = self.editor.readline(&question).unwrap_or_default();
+        let close = match answer.trim().to_lowercase().as_str() {
+            "l" | "link" => false,
+            "c" | "close" => true,
+            _ => return Ok(()),
+        };
+        let Some(target) = self.pick_duplicate(duplicates) else {
+            return Ok(());
         };
         
-        // Get completion conditions
-        println!("\n✅ 完了条件 (Completion conditions - enter conditions separated by comma):");
-        println!("   Example: すべてのテストがパス, コードレビュー完了");
-        let completion_input = self.editor.readline("> ").unwrap_or_default();
-        let completion_conditions: Vec<String> = if !completion_input.trim().is_empty() {
-            completion_input.split(',')
-                .map(|s| format!("[ ] {}", s.trim()))
-                .collect()
+        if close {
+            client.run_quick_actions(project_id, iid, &format!("/duplicate #{}", target)).await?;
+            println!("✅ Closed #{} as a duplicate of #{}", iid, target);
         } else {
-            vec!["[ ] 完了条件を追加してください".to_string()]
+            client.link_issues(project_id, iid, target).await?;
+            println!("🔗 Linked #{} to #{}", iid, target);
+        }
+        Ok(())
+    }
+    
+    fn pick_duplicate(&mut self, duplicates: &[DuplicateMatch]) -> Option<u64> {
+        if let [only] = duplicates {
+            return Some(only.iid);
+        }
+        let question = format!("Which one? [1-{}]: ", duplicates.len());
+        let answer = self.editor.readline(&question).ok()?;
+        match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=duplicates.len()).contains(&n) => Some(duplicates[n - 1].iid),
+            _ => {
+                println!("❌ Not one of the listed issues.");
+                None
+            }
+        }
+    }
+    
+    /// Rank project members for an issue and offer to assign the top pick.
+    /// With `assign` the top pick is assigned without asking.
+    pub async fn suggest_assignee(&mut self, iid: u64, assign: bool) -> Result<()> {
+        let (config, project_id) = self.require_project()?;
+        let (config, project_id) = (config.clone(), project_id.to_string());
+        let client = GitLabClient::new(&config);
+        
+        let context = ProjectContext::cached(&config, &project_id).await?;
+        println!("🔍 Comparing issue #{} with recently closed issues...", iid);
+        let issue = client.get_issue(&project_id, iid).await?;
+        let closed = client.list_recent_issues(
+            &project_id,
+            &[("state", "closed"), ("order_by", "updated_at")],
+            assignee::HISTORY_SIZE,
+        ).await?;
+        
+        let candidates = assignee::rank(&issue, &closed, &context);
+        let Some(top) = candidates.first().cloned() else {
+            anyhow::bail!("No project members in the cached context. Run '/update-context' first.");
         };
         
-        // Build the issue description
-        let mut description = format!("## 背景\n\n{}\n\n## 作業項目\n\n", background);
-        for (i, item) in work_items.iter().enumerate() {
-            description.push_str(&format!("{}. {}\n", i + 1, item));
+        println!("\n👥 Suggested assignees for #{}: {}", issue.iid, issue.title);
+        for (i, candidate) in candidates.iter().take(assignee::MAX_CANDIDATES).enumerate() {
+            println!("  {}. @{:<16} {:>3.0}%  {}", i + 1, candidate.username, candidate.confidence * 100.0, candidate.rationale());
         }
-        description.push_str("\n## 完了条件\n\n");
-        for condition in completion_conditions {
-            description.push_str(&format!("* {}\n", condition));
+        if !top.has_history() {
+            println!("⚠️  No similar closed issues or shared labels found; the ranking rests on workload and role only.");
         }
         
-        // Create the issue
-        println!("\n🔄 Creating issue with formatted template...");
-        let query = format!(
-            "Create a new GitLab issue with title: '{}' and description:\n{}",
-            title, description
-        );
+        if top.assigned {
+            println!("✅ @{} is already assigned to #{}.", top.username, issue.iid);
+            return Ok(());
+        }
+        if !assign {
+            let question = format!("Assign #{} to @{}? [y/N]: ", issue.iid, top.username);
+            let answer = self.editor.readline(&question).unwrap_or_default();
+            if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                return Ok(());
+            }
+        }
         
-        match self.query_with_context(&query).await {
-            Ok(response) => {
-                println!("\n✅ {}", response);
+        let user = client.find_user(&top.username).await?
+            .ok_or_else(|| anyhow::anyhow!("No GitLab user named @{}", top.username))?;
+        client.set_issue_assignees(&project_id, issue.iid, &[user.id]).await?;
+        println!("✅ Assigned #{} to @{}", issue.iid, top.username);
+        Ok(())
+    }
+    
+    /// Show a developer's assigned work, review queue, recent events and blockers, then a written summary of them
+    pub async fn summarize_developer(&mut self, username: &str, days: i64) -> Result<()> {
+        let (config, _) = self.require_project()?;
+        let config = config.clone();
+        let agent = KenAgent::build(&AgentConfig::from_llm(&config.llm)?);
+        
+        println!("🔄 Collecting activity for @{} over the last {} days...", username.trim_start_matches('@'), days);
+        let activity = DeveloperActivity::collect(&GitLabTools::new(config), username, days).await?;
+        println!("\n{}", activity.report());
+        
+        println!("🤖 Summarizing...");
+        KenAgent::stream_chat(&agent, &activity.summary_prompt(), Vec::new(), true).await?;
+        Ok(())
+    }
+    
+    /// Print the team progress report for `period`
+    pub async fn progress_report(&self, period: Period, format: ReportFormat) -> Result<()> {
+        let (config, project_id) = self.require_project()?;
+        // Milestones come from the cached context. Fetching a missing context here would
+        // print progress lines into the report, so the milestone section stays empty instead.
+        let context = ProjectContext::load(&config.profile, project_id)?;
+        let report = ProgressReport::collect(&GitLabTools::new(config.clone()), &context, period).await?;
+        println!("{}", report.render(format)?);
+        Ok(())
+    }
+    
+    async fn handle_report_command(&self, args: &str) -> Result<()> {
+        let mut period = Period::Weekly;
+        let mut format = ReportFormat::Markdown;
+        let mut args = args.split_whitespace();
+        while let Some(arg) = args.next() {
+            match arg {
+                "--daily" => period = Period::Daily,
+                "--weekly" => period = Period::Weekly,
+                "--format" => format = args.next().unwrap_or_default().parse()?,
+                _ => match arg.strip_prefix("--format=") {
+                    Some(value) => format = value.parse()?,
+                    None => anyhow::bail!("Usage: /report [--daily|--weekly] [--format md|text|json]"),
+                },
             }
-            Err(e) => {
-                println!("❌ {}", e);
+        }
+        self.progress_report(period, format).await
+    }
+    
+    /// Summarize an issue with its whole discussion and offer to post the summary as a comment.
+    /// With `post` the summary is posted without asking.
+    pub async fn summarize_issue(&mut self, iid: u64, post: bool) -> Result<()> {
+        let (config, project_id) = self.require_project()?;
+        let (config, project_id) = (config.clone(), project_id.to_string());
+        let agent_config = AgentConfig::from_llm(&config.llm)?;
+        let agent = KenAgent::build(&agent_config);
+        let client = GitLabClient::new(&config);
+        
+        println!("🔄 Fetching issue #{} and its discussion...", iid);
+        let (issue, discussions) = tokio::try_join!(
+            client.get_issue(&project_id, iid),
+            client.list_issue_discussions(&project_id, iid),
+        )?;
+        let thread = IssueThread { issue, discussions };
+        println!("💬 {} comments in {} threads", thread.comment_count(), thread.discussions.len());
+        
+        let summary = thread.summarize(&agent, agent_config.history_budget()).await?;
+        if summary.is_empty() {
+            anyhow::bail!("The model returned an empty summary");
+        }
+        
+        if !post {
+            let question = format!("Post this summary as a comment on #{}? [y/N]: ", iid);
+            let answer = self.editor.readline(&question).unwrap_or_default();
+            if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                return Ok(());
+            }
+        }
+        
+        client.create_issue_note(&project_id, iid, &thread.comment(&summary)).await?;
+        println!("✅ Posted the summary to #{}", iid);
+        Ok(())
+    }
+    
+    fn edit_draft(&mut self, draft: &mut IssueDraft) {
+        let field = self.editor
+            .readline("Edit [t]itle / [d]escription / [l]abels / [m]ilestone / [a]ssignee / [s]everity: ")
+            .unwrap_or_default();
+        match field.trim().to_lowercase().as_str() {
+            "t" | "title" => {
+                if let Some(title) = self.edit_line("Title: ", &draft.title).filter(|t| !t.is_empty()) {
+                    draft.title = title;
+                }
+            }
+            "d" | "description" => {
+                if let Some(description) = self.edit_text(&draft.description) {
+                    draft.description = description;
+                }
+            }
+            "l" | "labels" => {
+                if let Some(labels) = self.edit_line("Labels (comma-separated): ", &draft.labels.join(", ")) {
+                    draft.labels = labels.split(',').map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect();
+                }
+            }
+            "m" | "milestone" => {
+                let current = draft.milestone.clone().unwrap_or_default();
+                if let Some(milestone) = self.edit_line("Milestone (empty for none): ", &current) {
+                    draft.milestone = Some(milestone).filter(|m| !m.is_empty());
+                }
+            }
+            "a" | "assignee" => {
+                let current = draft.assignee.clone().unwrap_or_default();
+                if let Some(assignee) = self.edit_line("Assignee username (empty for none): ", &current) {
+                    draft.assignee = Some(assignee).filter(|a| !a.is_empty());
+                }
+            }
+            "s" | "severity" => {
+                let current = draft.severity.clone().unwrap_or_default();
+                let prompt = format!("Severity ({}, empty for none): ", issue_draft::SEVERITIES.join("/"));
+                if let Some(severity) = self.edit_line(&prompt, &current) {
+                    draft.severity = Some(severity).filter(|s| !s.is_empty());
+                }
+            }
+            _ => println!("❌ Unknown field."),
+        }
+    }
+    
+    /// Read a replacement for `current`, pre-filled so it can be tweaked rather than retyped
+    fn edit_line(&mut self, prompt: &str, current: &str) -> Option<String> {
+        self.editor.readline_with_initial(prompt, (current, "")).ok().map(|line| line.trim().to_string())
+    }
+    
+    /// Edit multi-line text in $VISUAL or $EDITOR, or line by line when neither is set
+    fn edit_text(&mut self, current: &str) -> Option<String> {
+        let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).ok()
+            .filter(|editor| !editor.trim().is_empty());
+        
+        let Some(editor) = editor else {
+            println!("Enter the new text; finish with a line containing only '.' (set $EDITOR to use your editor):");
+            let mut lines = Vec::new();
+            loop {
+                match self.editor.readline("") {
+                    Ok(line) if line.trim() == "." => break,
+                    Ok(line) => lines.push(line),
+                    Err(_) => return None,
+                }
             }
+            return Some(lines.join("\n"));
+        };
+        
+        let path = std::env::temp_dir().join(format!("ken-draft-{}.md", std::process::id()));
+        if let Err(e) = crate::config::write_private(&path, current.as_bytes()) {
+            println!("❌ Could not write {}: {}", path.display(), e);
+            return None;
         }
+        // $EDITOR may carry arguments, e.g. "code --wait"
+        let mut words = editor.split_whitespace();
+        let status = std::process::Command::new(words.next().unwrap_or_default())
+            .args(words)
+            .arg(&path)
+            .status();
+        let edited = match status {
+            Ok(status) if status.success() => std::fs::read_to_string(&path).ok(),
+            Ok(status) => {
+                println!("❌ {} exited with {}; keeping the previous text.", editor, status);
+                None
+            }
+            Err(e) => {
+                println!("❌ Could not run {}: {}", editor, e);
+                None
+            }
+        };
+        let _ = std::fs::remove_file(&path);
+        edited
     }
     
     async fn create_mr_with_template(&mut self) {
@@ -861,6 +1619,13 @@ tasks#"#.to_string()
             _ => "main".to_string()
         };
         
+        let template = prompts::load(PromptKind::MrTemplate);
+        if template.source != PromptSource::BuiltIn {
+            let branches = format!(", source branch: '{}', target branch: '{}'", source_branch, target_branch);
+            self.create_from_custom_template("merge request", &title, &template.text, &branches).await;
+            return;
+        }
+        
         // Get overview
         println!("\n📄 概要 (Overview - what changes and why):");
         let overview = match self.editor.readline("> ") {
@@ -934,64 +1699,83 @@ tasks#"#.to_string()
         }
     }
 
-    async fn analyze_workload_direct(&self, config: &Config) -> Result<()> {
+    pub async fn analyze_workload_direct(&self, config: &Config) -> Result<()> {
         let gitlab = GitLabTools::new(config.clone());
         
-        println!("🔄 Fetching project members...");
-        let members = gitlab.get_project_members().await?;
+        // One listing each for members, labels, issues and MRs, fetched together
+        println!("🔄 Fetching project members, labels, issues and merge requests...");
+        let (members, labels, all_issues, all_mrs) = tokio::try_join!(
+            gitlab.get_project_members(),
+            gitlab.get_project_labels(),
+            gitlab.get_all_open_issues(),
+            gitlab.get_all_open_mrs(),
+        )?;
         println!("👥 Found {} project members", members.len());
         
+        // Weight issues by their labels so a critical bug counts for more than a docs task
+        println!("🔄 Weighting project labels...");
+        let weights = self.analyze_labels_with_llm(&labels, &all_issues).await?;
+        
         println!("🔄 Analyzing individual workloads...");
         let mut workloads = Vec::new();
         
         for member in &members {
-            let issues = gitlab.get_issues_by_assignee(&member.username).await.unwrap_or_default();
-            let mrs = gitlab.get_mrs_by_assignee(&member.username).await.unwrap_or_default();
-            let load_score = issues.len() + (mrs.len() * 2);
+            let issues: Vec<GitLabIssue> = all_issues.iter()
+                .filter(|issue| issue.assignee_usernames().contains(&member.username.as_str()))
+                .cloned()
+                .collect();
+            let mrs = all_mrs.iter()
+                .filter(|mr| mr.assignee_usernames().contains(&member.username.as_str()))
+                .count();
+            let load_score = self.calculate_weighted_score(&issues, &weights) + (mrs * 2);
             
             if load_score > 0 {
-                workloads.push((member, issues.len(), mrs.len(), load_score));
+                let priority_labels = self.get_priority_labels(&issues, &weights);
+                workloads.push((member, issues.len(), mrs, load_score, priority_labels));
             }
         }
         
         // Sort by load score (highest first)
-        workloads.sort_by(|a, b| b.3.cmp(&a.3));
+        workloads.sort_by_key(|w| std::cmp::Reverse(w.3));
         
         println!("\n📊 **Team Workload Analysis**\n");
-        println!("| Full Name (username) | Role | Open Issues | Open MRs | Load Score | Status |");
-        println!("|---------------------|------|------------|----------|------------|--------|");
+        println!("| Full Name (username) | Role | Open Issues | Open MRs | Load Score | Priority Labels | Status |");
+        println!("|---------------------|------|------------|----------|------------|-----------------|--------|");
         
-        for (member, issues, mrs, load_score) in &workloads {
+        for (member, issues, mrs, load_score, priority_labels) in &workloads {
             let status = match *load_score {
                 score if score > 8 => "🔴 High",
                 score if score >= 4 => "🟡 Medium", 
                 _ => "🟢 Low"
             };
             
-            println!("| {} ({}) | {} | {} | {} | {} | {} |",
+            println!("| {} ({}) | {} | {} | {} | {} | {} | {} |",
                 member.name,
                 member.username,
                 member.role_name,
                 issues,
                 mrs,
                 load_score,
+                priority_labels,
                 status
             );
         }
         
         // Get unassigned issues
         println!("\n🔄 Checking for unassigned work...");
-        let all_issues = gitlab.get_all_open_issues().await?;
         let unassigned_issues: Vec<_> = all_issues.iter()
-            .filter(|issue| issue.assignee.is_none())
+            .filter(|issue| issue.assignee_usernames().is_empty())
             .collect();
+        let unassigned_high_priority = unassigned_issues.iter()
+            .filter(|issue| self.has_high_priority_labels(issue, &weights))
+            .count();
         
         println!("\n📈 **Summary & Recommendations:**");
         println!("- 🔴 High workload (>8): {} members", workloads.iter().filter(|w| w.3 > 8).count());
         println!("- 🟡 Medium workload (4-8): {} members", workloads.iter().filter(|w| w.3 >= 4 && w.3 <= 8).count());
         println!("- 🟢 Low workload (<4): {} members", workloads.iter().filter(|w| w.3 < 4).count());
         println!("- 📋 Total active members: {}", workloads.len());
-        println!("- ❓ Unassigned issues: {}", unassigned_issues.len());
+        println!("- ❓ Unassigned issues: {} ({} high priority)", unassigned_issues.len(), unassigned_high_priority);
         
         if !unassigned_issues.is_empty() {
             println!("\n🔗 **Unassigned Issues:**");
@@ -1006,15 +1790,19 @@ tasks#"#.to_string()
         Ok(())
     }
 
-    async fn analyze_workload_from_context(&self, config: &Config) -> Result<()> {
+    pub async fn analyze_workload_from_context(&self, config: &Config) -> Result<()> {
         if let Some(project_id) = &config.default_project_id {
-            let context = ProjectContext::load(project_id)?;
+            let context = ProjectContext::load(&config.profile, project_id)?;
             
             if context.is_stale() {
                 println!("⚠️  Context data is stale. Run `/update-context` first for accurate analysis.");
                 return Ok(());
             }
             
+            if !context.fetch_errors.is_empty() {
+                println!("⚠️  Workload data is incomplete: {} fetches failed during the last update.", context.fetch_errors.len());
+            }
+            
             if context.workload_data.user_assignments.is_empty() && context.workload_data.total_open_issues == 0 {
                 println!("⚠️  No workload data found. Run `/update-context` to fetch detailed workload information.");
                 return Ok(());
@@ -1024,7 +1812,7 @@ tasks#"#.to_string()
             
             // Convert workload data to sorted vector
             let mut workloads: Vec<_> = context.workload_data.user_assignments.values().collect();
-            workloads.sort_by(|a, b| b.total_score.cmp(&a.total_score));
+            workloads.sort_by_key(|w| std::cmp::Reverse(w.total_score));
             
             println!("\n| Full Name (username) | Role | Issues | MRs | Total Score | Status |");
             println!("|---------------------|------|--------|-----|-------------|--------|");
@@ -1091,7 +1879,7 @@ tasks#"#.to_string()
         Ok(())
     }
 
-    async fn analyze_labels_with_llm(&self, labels: &[String], issues: &[crate::gitlab_tools::GitLabIssue]) -> Result<std::collections::HashMap<String, f64>> {
+    async fn analyze_labels_with_llm(&self, labels: &[String], issues: &[GitLabIssue]) -> Result<std::collections::HashMap<String, f64>> {
         if let Some(ref agent) = self.agent {
             // Create a summary of label usage
             let mut label_usage = std::collections::HashMap::new();
@@ -1133,12 +1921,11 @@ Return ONLY a JSON object mapping label names to numeric weights (1.0-3.0):
                         return Ok(weights);
                     } else {
                         // Fallback: extract JSON from response text
-                        if let Some(start) = response_text.find('{') {
-                            if let Some(end) = response_text.rfind('}') {
-                                let json_part = &response_text[start..=end];
-                                if let Ok(weights) = serde_json::from_str::<std::collections::HashMap<String, f64>>(json_part) {
-                                    return Ok(weights);
-                                }
+                        if let Some(start) = response_text.find('{')
+                            && let Some(end) = response_text.rfind('}') {
+                            let json_part = &response_text[start..=end];
+                            if let Ok(weights) = serde_json::from_str::<std::collections::HashMap<String, f64>>(json_part) {
+                                return Ok(weights);
                             }
                         }
                     }
@@ -1162,21 +1949,21 @@ Return ONLY a JSON object mapping label names to numeric weights (1.0-3.0):
         Ok(default_weights)
     }
 
-    fn calculate_weighted_score(&self, issues: &[crate::gitlab_tools::GitLabIssue], weights: &std::collections::HashMap<String, f64>) -> usize {
+    fn calculate_weighted_score(&self, issues: &[GitLabIssue], weights: &std::collections::HashMap<String, f64>) -> usize {
         issues.iter()
             .map(|issue| self.calculate_issue_weight(issue, weights))
             .sum::<f64>() as usize
     }
 
-    fn calculate_issue_weight(&self, issue: &crate::gitlab_tools::GitLabIssue, weights: &std::collections::HashMap<String, f64>) -> f64 {
+    fn calculate_issue_weight(&self, issue: &GitLabIssue, weights: &std::collections::HashMap<String, f64>) -> f64 {
         let base_weight = 1.0;
         let label_multiplier = issue.labels.iter()
             .map(|label| weights.get(label).unwrap_or(&1.5))
-            .fold(1.0, |acc, &weight| acc * weight.min(3.0).max(1.0));
+            .fold(1.0, |acc, &weight| acc * weight.clamp(1.0, 3.0));
         base_weight * label_multiplier
     }
 
-    fn get_priority_labels(&self, issues: &[crate::gitlab_tools::GitLabIssue], weights: &std::collections::HashMap<String, f64>) -> String {
+    fn get_priority_labels(&self, issues: &[GitLabIssue], weights: &std::collections::HashMap<String, f64>) -> String {
         let mut high_priority_labels = std::collections::HashSet::new();
         for issue in issues {
             for label in &issue.labels {
@@ -1197,14 +1984,26 @@ Return ONLY a JSON object mapping label names to numeric weights (1.0-3.0):
         }
     }
 
-    fn has_high_priority_labels(&self, issue: &crate::gitlab_tools::GitLabIssue, weights: &std::collections::HashMap<String, f64>) -> bool {
+    fn has_high_priority_labels(&self, issue: &GitLabIssue, weights: &std::collections::HashMap<String, f64>) -> bool {
         issue.labels.iter().any(|label| weights.get(label).unwrap_or(&1.5) >= &2.0)
     }
 
 
-    async fn cleanup(&mut self) {
+    pub async fn cleanup(&mut self) {
         if let Some(mut process) = self.mcp_server_process.take() {
             let _ = process.kill().await;
         }
     }
-}
\ No newline at end of file
+}
+
+/// List the changes a dry run recorded instead of making
+fn print_dry_run(tool_calls: &[ToolCall]) {
+    let skipped: Vec<&ToolCall> = tool_calls.iter().filter(|call| call.dry_run).collect();
+    if skipped.is_empty() {
+        return;
+    }
+    println!("\n🧪 Dry run: {} change{} not made:", skipped.len(), if skipped.len() == 1 { "" } else { "s" });
+    for call in skipped {
+        println!("  - {} {}", call.name, call.arguments);
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use rig::completion::Prompt;
+use serde::{Deserialize, Serialize};
+use crate::agent::{strip_reasoning, LlmAgent};
+use crate::context::ProjectContext;
+use crate::gitlab_client::{GitLabClient, GitLabIssue, NewIssue};
+
+/// Severities a draft can carry, most severe first
+pub const SEVERITIES: [&str; 4] = ["critical", "high", "medium", "low"];
+
+/// An issue proposed by the LLM, reviewed by the user before anything is created
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IssueDraft {
+    pub title: String,
+    pub description: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub milestone: Option<String>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub severity: Option<String>,
+}
+
+impl IssueDraft {
+    /// Ask the LLM to turn a free-form report into a draft that follows `template`
+    pub async fn generate(agent: &LlmAgent, report: &str, template: &str, context: &ProjectContext) -> Result<Self> {
+        let labels: Vec<&str> = context.labels.iter().map(|l| l.name.as_str()).collect();
+        let milestones: Vec<&str> = active_milestones(context).collect();
+        let users: Vec<&str> = context.users.iter().map(|u| u.username.as_str()).collect();
+
+        let prompt = format!(
+            "Turn this report into a GitLab issue for project {project}. Do not call any tools.\n\
+             Reply with ONLY a JSON object of this shape:\n\
+             {{\"title\": \"...\", \"description\": \"...\", \"labels\": [\"...\"], \"milestone\": null, \"assignee\": null, \"severity\": \"medium\"}}\n\n\
+             - title: short and specific, under 80 characters\n\
+             - description: Markdown that follows this template:\n{template}\n\
+             - labels: zero or more of: {labels}\n\
+             - milestone: one of: {milestones}; or null\n\
+             - assignee: a username from: {users}; or null unless the report makes the owner clear\n\
+             - severity: one of {severities}\n\
+             Write in the language of the report.\n\n\
+             Report: {report}",
+            project = context.project_id,
+            template = template.trim(),
+            labels = list_or_none(&labels),
+            milestones = list_or_none(&milestones),
+            users = list_or_none(&users),
+            severities = SEVERITIES.join(", "),
+            report = report.trim(),
+        );
+
+        let response = agent.prompt(prompt.as_str()).await?;
+        Self::parse(&response)
+    }
+
+    /// Pull the JSON object out of a reply, skipping any reasoning before it
+    fn parse(response: &str) -> Result<Self> {
+        let text = strip_reasoning(response);
+        let json = match (text.find('{'), text.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &text[start..=end],
+            _ => anyhow::bail!("The model did not return an issue draft. Reply was:\n{}", text.trim()),
+        };
+        let draft: IssueDraft = serde_json::from_str(json)
+            .map_err(|e| anyhow::anyhow!("The model returned a malformed issue draft ({}):\n{}", e, json))?;
+        if draft.title.trim().is_empty() {
+            anyhow::bail!("The model returned a draft without a title");
+        }
+        Ok(draft)
+    }
+
+    /// Keep only labels, milestones and assignees that exist in the project.
+    /// Returns a note for everything that was dropped or corrected.
+    pub fn validate(&mut self, context: &ProjectContext) -> Vec<String> {
+        let mut notes = Vec::new();
+
+        let mut labels = Vec::new();
+        for label in self.labels.drain(..) {
+            match context.labels.iter().find(|l| l.name.eq_ignore_ascii_case(label.trim())) {
+                Some(known) if !labels.contains(&known.name) => labels.push(known.name.clone()),
+                Some(_) => {}
+                None => notes.push(format!("Dropped label '{}': not a label in this project", label)),
+            }
+        }
+        self.labels = labels;
+
+        if let Some(severity) = self.severity.take().filter(|s| !s.trim().is_empty()) {
+            let normalized = severity.trim().to_lowercase();
+            if SEVERITIES.contains(&normalized.as_str()) {
+                // Projects that track severity with scoped labels get the label as well
+                if let Some(label) = context.labels.iter()
+                    .find(|l| l.name.eq_ignore_ascii_case(&format!("severity::{}", normalized)))
+                    && !self.labels.contains(&label.name) {
+                    self.labels.push(label.name.clone());
+                }
+                self.severity = Some(normalized);
+            } else {
+                notes.push(format!("Dropped severity '{}': expected one of {}", severity, SEVERITIES.join(", ")));
+            }
+        }
+
+        if let Some(milestone) = self.milestone.take().filter(|m| !m.trim().is_empty()) {
+            match active_milestones(context).find(|title| title.eq_ignore_ascii_case(milestone.trim())) {
+                Some(title) => self.milestone = Some(title.to_string()),
+                None => notes.push(format!("Dropped milestone '{}': not an active milestone", milestone)),
+            }
+        }
+
+        if let Some(assignee) = self.assignee.take().filter(|a| !a.trim().is_empty()) {
+            let username = assignee.trim().trim_start_matches('@');
+            match context.users.iter().find(|u| u.username.eq_ignore_ascii_case(username)) {
+                Some(user) => self.assignee = Some(user.username.clone()),
+                None => notes.push(format!("Dropped assignee '{}': not a member of this project", assignee)),
+            }
+        }
+
+        notes
+    }
+
+    /// Draft as shown for review
+    pub fn preview(&self) -> String {
+        let mut text = format!("📌 Title:     {}\n", self.title);
+        text.push_str(&format!("🏷️  Labels:    {}\n", if self.labels.is_empty() { "-".to_string() } else { self.labels.join(", ") }));
+        text.push_str(&format!("🎯 Milestone: {}\n", self.milestone.as_deref().unwrap_or("-")));
+        text.push_str(&format!("👤 Assignee:  {}\n", self.assignee.as_deref().map_or("-".to_string(), |a| format!("@{}", a))));
+        text.push_str(&format!("🔥 Severity:  {}\n", self.severity.as_deref().unwrap_or("-")));
+        text.push_str(&format!("📄 Description:\n{}\n", self.description.trim()));
+        text
+    }
+
+    /// Create the issue, resolving the milestone and assignee to their GitLab ids
+    pub async fn create(&self, client: &GitLabClient, project_id: &str) -> Result<GitLabIssue> {
+        let milestone_id = match &self.milestone {
+            Some(title) => Some(
+                client.list_milestones(project_id).await?
+                    .into_iter()
+                    .find(|m| m.title == *title)
+                    .ok_or_else(|| anyhow::anyhow!("Milestone '{}' no longer exists", title))?
+                    .id,
+            ),
+            None => None,
+        };
+        let assignee_ids = match &self.assignee {
+            Some(username) => vec![
+                client.find_user(username).await?
+                    .ok_or_else(|| anyhow::anyhow!("No GitLab user named @{}", username))?
+                    .id,
+            ],
+            None => Vec::new(),
+        };
+
+        // Without a severity label the severity would be lost, so it goes in the description
+        let mut description = self.description.trim().to_string();
+        if let Some(severity) = &self.severity
+            && !self.labels.iter().any(|l| l.eq_ignore_ascii_case(&format!("severity::{}", severity))) {
+            description = format!("**Severity:** {}\n\n{}", severity, description);
+        }
+
+        let issue = NewIssue {
+            title: self.title.trim().to_string(),
+            description,
+            labels: self.labels.join(","),
+            milestone_id,
+            assignee_ids,
+        };
+        Ok(client.create_issue(project_id, &issue).await?)
+    }
+}
+
+fn active_milestones(context: &ProjectContext) -> impl Iterator<Item = &str> {
+    context.milestones.iter()
+        .filter(|m| m.state == "active")
+        .map(|m| m.title.as_str())
+}
+
+fn list_or_none(items: &[&str]) -> String {
+    if items.is_empty() {
+        "(none)".to_string()
+    } else {
+        items.join(", ")
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use rig::completion::Prompt;
+use crate::agent::{strip_reasoning, KenAgent, LlmAgent};
+use crate::conversation::{estimate_tokens, CHARS_PER_TOKEN};
+use crate::gitlab_client::{GitLabDiscussion, GitLabIssue, GitLabNote};
+
+/// Tokens set aside for the instructions around the thread in each prompt
+const PROMPT_OVERHEAD: usize = 600;
+/// Smallest chunk worth a request, however small the context window
+const MIN_CHUNK_TOKENS: usize = 1000;
+/// Rounds of condensing before whatever is left is cut to fit
+const MAX_LEVELS: usize = 4;
+
+/// An issue with every discussion thread on it
+pub struct IssueThread {
+    pub issue: GitLabIssue,
+    pub discussions: Vec<GitLabDiscussion>,
+}
+
+impl IssueThread {
+    /// Comments written by people, leaving out system notes like "changed the milestone"
+    pub fn comment_count(&self) -> usize {
+        self.discussions.iter()
+            .flat_map(|d| &d.notes)
+            .filter(|note| !note.system)
+            .count()
+    }
+
+    /// The summary as posted on the issue, with a header saying how much of the thread it covers
+    pub fn comment(&self, summary: &str) -> String {
+        format!(
+            "**Summary by Ken** of {} comments, as of {}\n\n{}",
+            self.comment_count(),
+            chrono::Local::now().format("%Y-%m-%d %H:%M"),
+            summary
+        )
+    }
+
+    /// Write a structured summary, streaming the final pass to the terminal.
+    ///
+    /// `budget` is the number of prompt tokens the model accepts. Threads that don't fit are
+    /// cut into chunks, each chunk is summarized, and the summaries are condensed again until
+    /// they fit, so even very long discussions end up in a single final prompt.
+    pub async fn summarize(&self, agent: &LlmAgent, budget: usize) -> Result<String> {
+        let chunk_tokens = budget.saturating_sub(PROMPT_OVERHEAD).max(MIN_CHUNK_TOKENS);
+        let mut blocks = vec![format!(
+            "Description by @{}:\n{}",
+            self.issue.author.username,
+            self.issue.description.as_deref().filter(|d| !d.trim().is_empty()).unwrap_or("(none)")
+        )];
+        blocks.extend(self.discussions.iter().filter_map(render_discussion));
+
+        let mut condensed = false;
+        for level in 1..=MAX_LEVELS {
+            if blocks.iter().map(|b| estimate_tokens(b)).sum::<usize>() <= chunk_tokens {
+                break;
+            }
+            let chunks = pack(&blocks, chunk_tokens);
+            println!("🧩 Thread too long for one request; condensing {} parts (pass {})...", chunks.len(), level);
+            let mut summaries = Vec::with_capacity(chunks.len());
+            for (i, chunk) in chunks.iter().enumerate() {
+                summaries.push(self.summarize_chunk(agent, chunk, i + 1, chunks.len()).await?);
+            }
+            blocks = summaries;
+            condensed = true;
+        }
+
+        let mut discussion = blocks.join("\n\n");
+        let max_chars = chunk_tokens * CHARS_PER_TOKEN;
+        if discussion.chars().count() > max_chars {
+            discussion = discussion.chars().take(max_chars).collect();
+        }
+
+        let prompt = format!(
+            "Summarize GitLab issue #{iid} \"{title}\" for someone who has not read it. Do not call any tools.\n\
+             Reply in Markdown with exactly these sections, writing \"None\" under any with nothing to report:\n\
+             ### Core problem\n### Key technical details\n### Decisions made\n### Open questions\n### Suggested next steps\n\n\
+             State: {state}. Labels: {labels}. Assignees: {assignees}.\n\n\
+             {kind}:\n{discussion}",
+            iid = self.issue.iid,
+            title = self.issue.title,
+            state = self.issue.state,
+            labels = if self.issue.labels.is_empty() { "none".to_string() } else { self.issue.labels.join(", ") },
+            assignees = match self.issue.assignee_usernames() {
+                names if names.is_empty() => "none".to_string(),
+                names => names.iter().map(|n| format!("@{}", n)).collect::<Vec<_>>().join(", "),
+            },
+            kind = if condensed { "Description and discussion, condensed from the full thread" } else { "Description and discussion" },
+            discussion = discussion,
+        );
+        let reply = KenAgent::stream_chat(agent, &prompt, Vec::new(), true).await?;
+        Ok(strip_reasoning(&reply).to_string())
+    }
+
+    async fn summarize_chunk(&self, agent: &LlmAgent, chunk: &str, part: usize, parts: usize) -> Result<String> {
+        let prompt = format!(
+            "This is part {part} of {parts} of the thread on GitLab issue #{iid} \"{title}\". \
+             Summarize it in a few bullet points. Keep technical details (errors, versions, file names), \
+             decisions and who made them, and questions still unanswered. Do not call any tools.\n\n{chunk}",
+            iid = self.issue.iid,
+            title = self.issue.title,
+        );
+        let reply = agent.prompt(prompt.as_str()).await?;
+        Ok(format!("Summary of part {} of {}:\n{}", part, parts, strip_reasoning(&reply)))
+    }
+}
+
+/// A thread as plain text, replies indented under the first comment. System-only threads become a single line.
+fn render_discussion(discussion: &GitLabDiscussion) -> Option<String> {
+    let mut notes = discussion.notes.iter();
+    let first = notes.next()?;
+    let mut text = render_note(first, "");
+    if first.resolvable && discussion.notes.iter().all(|note| note.resolved) {
+        text.push_str(" [resolved]");
+    }
+    for reply in notes {
+        text.push('\n');
+        text.push_str(&render_note(reply, "  ↳ "));
+    }
+    Some(text)
+}
+
+fn render_note(note: &GitLabNote, prefix: &str) -> String {
+    let date = note.created_at.get(..10).unwrap_or(&note.created_at);
+    if note.system {
+        format!("{}· {} @{} {}", prefix, date, note.author.username, note.body.trim())
+    } else {
+        format!("{}@{} ({}): {}", prefix, note.author.username, date, note.body.trim())
+    }
+}
+
+/// Group blocks into chunks of at most `max_tokens`, splitting any block that is too big on its own
+fn pack(blocks: &[String], max_tokens: usize) -> Vec<String> {
+    let max_chars = max_tokens * CHARS_PER_TOKEN;
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    let pieces = blocks.iter().flat_map(|block| {
+        let chars: Vec<char> = block.chars().collect();
+        chars.chunks(max_chars).map(|piece| piece.iter().collect::<String>()).collect::<Vec<_>>()
+    });
+    for piece in pieces {
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(&piece) > max_tokens {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&piece);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
@@ -1,20 +1,43 @@
+mod activity;
 mod agent;
+mod assignee;
+mod audit;
+mod bot;
+mod cli;
 mod config;
 mod context;
+mod conversation;
+mod credentials;
+mod gitlab_client;
 mod interactive;
+mod issue_draft;
+mod issue_summary;
 mod mcp_client;
+mod prompts;
+mod report;
+mod session;
+mod similarity;
+mod util;
 mod gitlab_tools;
 
 use anyhow::Result;
+use clap::Parser;
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
+    let args = cli::Cli::parse();
+
+    // Run a single subcommand non-interactively when one is given
+    if let Some(command) = args.command {
+        return Ok(cli::run(args.profile, args.project, args.dry_run, command).await);
+    }
+
     println!("🚀 Ken - GitLab Assistant");
     println!("Starting interactive mode...\n");
-    
-    // Start interactive session directly
-    let mut session = interactive::KenSession::new().await?;
+
+    let mut session = interactive::KenSession::new(args.profile, args.project, args.dry_run).await?;
     session.start_interactive().await?;
-    
-    Ok(())
+
+    Ok(ExitCode::SUCCESS)
 }
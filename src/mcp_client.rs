@@ -30,7 +30,7 @@ impl MCPClient {
     }
 
     async fn _request(&self, endpoint: &str, params: Option<serde_json::Value>, options: RequestOptions) -> Result<serde_json::Value> {
-        Ok(self.inner.request(endpoint, params, options).await?)
+        self.inner.request(endpoint, params, options).await
     }
 
     pub async fn get_tools_list(&self) -> Result<ToolsListResponse> {
@@ -43,6 +43,7 @@ impl MCPClient {
         Ok(tools)
     }
 
+    #[allow(dead_code)]
     pub async fn run_tool(&self, tool_name: &str, tool_arguments: serde_json::Value) -> Result<serde_json::Value> {
         let jsonrpc_method = "tools/call";
         let jsonrpc_params = serde_json::json!({
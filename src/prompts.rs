@@ -0,0 +1,117 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prompts and templates that can be overridden with a file of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    System,
+    IssueTemplate,
+    MrTemplate,
+}
+
+impl PromptKind {
+    pub const ALL: [PromptKind; 3] = [PromptKind::System, PromptKind::IssueTemplate, PromptKind::MrTemplate];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            PromptKind::System => "system_prompt.md",
+            PromptKind::IssueTemplate => "issue_format.md",
+            PromptKind::MrTemplate => "MR_format.md",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            PromptKind::System => "System prompt",
+            PromptKind::IssueTemplate => "Issue template",
+            PromptKind::MrTemplate => "MR template",
+        }
+    }
+
+    fn builtin(self) -> &'static str {
+        match self {
+            PromptKind::System => include_str!("../prompts/system_prompt.md"),
+            PromptKind::IssueTemplate => include_str!("../prompts/issue_format.md"),
+            PromptKind::MrTemplate => include_str!("../prompts/MR_format.md"),
+        }
+    }
+}
+
+/// Where the text of a prompt came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptSource {
+    /// `.ken/<file>` in the current directory or one of its parents
+    Project(PathBuf),
+    /// `~/.ken/prompts/<file>`
+    User(PathBuf),
+    BuiltIn,
+}
+
+impl PromptSource {
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            PromptSource::Project(path) | PromptSource::User(path) => Some(path),
+            PromptSource::BuiltIn => None,
+        }
+    }
+}
+
+impl fmt::Display for PromptSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PromptSource::Project(path) => write!(f, "{} (project)", path.display()),
+            PromptSource::User(path) => write!(f, "{} (user)", path.display()),
+            PromptSource::BuiltIn => f.write_str("built-in default"),
+        }
+    }
+}
+
+pub struct Prompt {
+    pub text: String,
+    pub source: PromptSource,
+}
+
+/// Load a prompt, preferring the project's `.ken/` over `~/.ken/prompts/` over the built-in default
+pub fn load(kind: PromptKind) -> Prompt {
+    let candidates = [
+        project_prompt_path(kind).map(PromptSource::Project),
+        user_prompt_path(kind).map(PromptSource::User),
+    ];
+
+    for source in candidates.into_iter().flatten() {
+        let Some(path) = source.path() else { continue };
+        match fs::read_to_string(path) {
+            Ok(text) if !text.trim().is_empty() => return Prompt { text, source },
+            Ok(_) => println!("⚠️  Ignoring empty prompt file {}", path.display()),
+            Err(e) => println!("⚠️  Could not read {}: {}", path.display(), e),
+        }
+    }
+
+    Prompt {
+        text: kind.builtin().to_string(),
+        source: PromptSource::BuiltIn,
+    }
+}
+
+/// Nearest `.ken/<file>` walking up from the current directory. The home directory
+/// is skipped because `~/.ken` holds ken's own settings, not project prompts.
+fn project_prompt_path(kind: PromptKind) -> Option<PathBuf> {
+    let home = dirs::home_dir();
+    let cwd = std::env::current_dir().ok()?;
+
+    cwd.ancestors()
+        .filter(|dir| Some(*dir) != home.as_deref())
+        .map(|dir| dir.join(".ken").join(kind.file_name()))
+        .find(|path| path.is_file())
+}
+
+fn user_prompt_path(kind: PromptKind) -> Option<PathBuf> {
+    let path = dirs::home_dir()?.join(".ken").join("prompts").join(kind.file_name());
+    path.is_file().then_some(path)
+}
+
+/// Directory where user-wide overrides go, for display
+pub fn user_prompt_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ken").join("prompts"))
+}
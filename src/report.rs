@@ -0,0 +1,403 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use std::str::FromStr;
+use crate::context::ProjectContext;
+use crate::gitlab_tools::{GitLabIssue, GitLabMR, GitLabTools};
+
+/// Unscoped labels that mark an issue as high priority
+const PRIORITY_LABELS: [&str; 5] = ["critical", "blocker", "urgent", "p0", "p1"];
+/// Label scopes that rank priority, as in `priority::high` or `severity::critical`
+const PRIORITY_SCOPES: [&str; 4] = ["priority", "severity", "prio", "sev"];
+/// Values of those scopes that count as high priority
+const PRIORITY_VALUES: [&str; 7] = ["critical", "blocker", "urgent", "highest", "high", "p0", "p1"];
+/// Labels, or values of a scoped label such as `type::bug`, that mark an issue as a bug
+const BUG_LABELS: [&str; 2] = ["bug", "defect"];
+/// Contributors listed in the report
+const MAX_CONTRIBUTORS: usize = 5;
+/// Items listed per section in Markdown and text; JSON always has everything
+const MAX_ITEMS_SHOWN: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Daily,
+    Weekly,
+}
+
+impl Period {
+    pub fn days(self) -> i64 {
+        match self {
+            Period::Daily => 1,
+            Period::Weekly => 7,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Period::Daily => "Daily",
+            Period::Weekly => "Weekly",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "text" | "txt" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => anyhow::bail!("Unknown report format '{}'. Use md, text or json.", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportIssue {
+    pub iid: u64,
+    pub title: String,
+    pub author: String,
+    pub assignees: Vec<String>,
+    pub labels: Vec<String>,
+    pub web_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportMergeRequest {
+    pub iid: u64,
+    pub title: String,
+    pub author: String,
+    pub merged_at: Option<String>,
+    pub web_url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Contributor {
+    pub username: String,
+    pub merged_mrs: usize,
+    pub closed_issues: usize,
+    pub opened_issues: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MilestoneProgress {
+    pub title: String,
+    pub due_date: Option<String>,
+    pub closed_issues: usize,
+    pub total_issues: usize,
+}
+
+/// Team digest for one period, as shown by `/report` and `ken report`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressReport {
+    pub project_id: String,
+    pub period: Period,
+    pub since: String,
+    pub until: String,
+    pub created_issues: Vec<ReportIssue>,
+    pub closed_issues: Vec<ReportIssue>,
+    pub merged_mrs: Vec<ReportMergeRequest>,
+    pub top_contributors: Vec<Contributor>,
+    pub critical_bugs: Vec<ReportIssue>,
+    /// Active milestones from the cached context; empty until `/update-context` has run
+    pub milestones: Vec<MilestoneProgress>,
+}
+
+impl ProgressReport {
+    pub async fn collect(tools: &GitLabTools, context: &ProjectContext, period: Period) -> Result<Self> {
+        let until = Utc::now();
+        let since = until - Duration::days(period.days());
+
+        let (created, closed, merged, open) = tokio::try_join!(
+            tools.get_issues_created_since(since),
+            tools.get_issues_closed_since(since),
+            tools.get_mrs_merged_since(since),
+            tools.get_all_open_issues(),
+        )?;
+
+        let active: Vec<_> = context.milestones.iter().filter(|m| m.state == "active").collect();
+        let milestone_issues = futures::future::try_join_all(
+            active.iter().map(|milestone| tools.get_milestone_issues(&milestone.title))
+        ).await?;
+        let milestones = active.iter().zip(&milestone_issues)
+            .map(|(milestone, issues)| MilestoneProgress {
+                title: milestone.title.clone(),
+                due_date: milestone.due_date.clone(),
+                closed_issues: issues.iter().filter(|issue| issue.state == "closed").count(),
+                total_issues: issues.len(),
+            })
+            .collect();
+
+        Ok(Self {
+            project_id: context.project_id.clone(),
+            period,
+            since: since.to_rfc3339(),
+            until: until.to_rfc3339(),
+            top_contributors: top_contributors(&created, &closed, &merged),
+            critical_bugs: open.iter().filter(|issue| is_critical(issue)).map(ReportIssue::from).collect(),
+            created_issues: created.iter().map(ReportIssue::from).collect(),
+            closed_issues: closed.iter().map(ReportIssue::from).collect(),
+            merged_mrs: merged.iter().map(ReportMergeRequest::from).collect(),
+            milestones,
+        })
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        Ok(match format {
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Text => self.to_text(),
+            ReportFormat::Json => serde_json::to_string_pretty(self)?,
+        })
+    }
+
+    /// Ready to paste into meeting notes or an issue
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# {} progress report: project {}\n\n", self.period.title(), self.project_id);
+        md.push_str(&format!("_{} to {}_\n\n", date(&self.since), date(&self.until)));
+
+        md.push_str("## Summary\n\n");
+        md.push_str(&format!("- Issues created: {}\n", self.created_issues.len()));
+        md.push_str(&format!("- Issues closed: {}\n", self.closed_issues.len()));
+        md.push_str(&format!("- Merge requests merged: {}\n", self.merged_mrs.len()));
+        md.push_str(&format!("- Open critical bugs: {}\n", self.critical_bugs.len()));
+
+        md.push_str(&format!("\n## Issues closed ({})\n\n", self.closed_issues.len()));
+        md.push_str(&markdown_issues(&self.closed_issues));
+        md.push_str(&format!("\n## Issues created ({})\n\n", self.created_issues.len()));
+        md.push_str(&markdown_issues(&self.created_issues));
+
+        md.push_str(&format!("\n## Merge requests merged ({})\n\n", self.merged_mrs.len()));
+        for mr in self.merged_mrs.iter().take(MAX_ITEMS_SHOWN) {
+            md.push_str(&format!("- [!{}]({}) {} (@{})\n", mr.iid, mr.web_url, mr.title, mr.author));
+        }
+        md.push_str(&more(self.merged_mrs.len(), "- "));
+
+        md.push_str("\n## Top contributors\n\n");
+        if self.top_contributors.is_empty() {
+            md.push_str("No activity in this period.\n");
+        } else {
+            md.push_str("| Contributor | MRs merged | Issues closed | Issues opened |\n|---|---|---|---|\n");
+            for c in &self.top_contributors {
+                md.push_str(&format!("| @{} | {} | {} | {} |\n", c.username, c.merged_mrs, c.closed_issues, c.opened_issues));
+            }
+        }
+
+        md.push_str(&format!("\n## Open critical bugs ({})\n\n", self.critical_bugs.len()));
+        md.push_str(&markdown_issues(&self.critical_bugs));
+
+        md.push_str("\n## Milestones\n\n");
+        if self.milestones.is_empty() {
+            md.push_str("No active milestones in the cached context.\n");
+        } else {
+            md.push_str("| Milestone | Due | Progress |\n|---|---|---|\n");
+            for m in &self.milestones {
+                md.push_str(&format!("| {} | {} | {} |\n", m.title, m.due_date.as_deref().unwrap_or("-"), m.progress()));
+            }
+        }
+        md
+    }
+
+    /// Terminal-friendly version of the same report
+    pub fn to_text(&self) -> String {
+        let mut text = format!("📊 {} progress report for project {} ({} to {})\n",
+            self.period.title(), self.project_id, date(&self.since), date(&self.until));
+
+        text.push_str(&format!("\n✅ Issues closed: {}\n", self.closed_issues.len()));
+        text.push_str(&text_issues(&self.closed_issues));
+        text.push_str(&format!("\n🆕 Issues created: {}\n", self.created_issues.len()));
+        text.push_str(&text_issues(&self.created_issues));
+
+        text.push_str(&format!("\n🔀 Merge requests merged: {}\n", self.merged_mrs.len()));
+        for mr in self.merged_mrs.iter().take(MAX_ITEMS_SHOWN) {
+            text.push_str(&format!("  - !{} {} (@{})\n", mr.iid, mr.title, mr.author));
+        }
+        text.push_str(&more(self.merged_mrs.len(), "  "));
+
+        text.push_str("\n🏆 Top contributors\n");
+        if self.top_contributors.is_empty() {
+            text.push_str("  No activity in this period.\n");
+        }
+        for (i, c) in self.top_contributors.iter().enumerate() {
+            text.push_str(&format!("  {}. @{}: {} MRs merged, {} issues closed, {} issues opened\n",
+                i + 1, c.username, c.merged_mrs, c.closed_issues, c.opened_issues));
+        }
+
+        text.push_str(&format!("\n🚨 Open critical bugs: {}\n", self.critical_bugs.len()));
+        text.push_str(&text_issues(&self.critical_bugs));
+
+        text.push_str("\n🎯 Milestones\n");
+        if self.milestones.is_empty() {
+            text.push_str("  No active milestones in the cached context. Run '/update-context' to fetch them.\n");
+        }
+        for m in &self.milestones {
+            let due = m.due_date.as_deref().map(|d| format!(" (due {})", d)).unwrap_or_default();
+            text.push_str(&format!("  - {}{}: {}\n", m.title, due, m.progress()));
+        }
+        text
+    }
+}
+
+impl MilestoneProgress {
+    /// e.g. "12/20 issues closed (60%)"
+    fn progress(&self) -> String {
+        let percent = (self.closed_issues * 100).checked_div(self.total_issues).unwrap_or(0);
+        format!("{}/{} issues closed ({}%)", self.closed_issues, self.total_issues, percent)
+    }
+}
+
+impl From<&GitLabIssue> for ReportIssue {
+    fn from(issue: &GitLabIssue) -> Self {
+        ReportIssue {
+            iid: issue.iid,
+            title: issue.title.clone(),
+            author: issue.author.username.clone(),
+            assignees: issue.assignee_usernames().into_iter().map(str::to_string).collect(),
+            labels: issue.labels.clone(),
+            web_url: issue.web_url.clone(),
+        }
+    }
+}
+
+impl From<&GitLabMR> for ReportMergeRequest {
+    fn from(mr: &GitLabMR) -> Self {
+        ReportMergeRequest {
+            iid: mr.iid,
+            title: mr.title.clone(),
+            author: mr.author.username.clone(),
+            merged_at: mr.merged_at.clone(),
+            web_url: mr.web_url.clone(),
+        }
+    }
+}
+
+/// Whether an issue is a bug (`bug`, `type::bug`) with a priority label (`critical`, `P1`, `severity::high`).
+/// Labels are matched whole, so `effort::high` or `P10` don't count.
+fn is_critical(issue: &GitLabIssue) -> bool {
+    let labels: Vec<(Option<String>, String)> = issue.labels.iter().map(|label| split_label(label)).collect();
+    let bug = labels.iter().any(|(_, value)| BUG_LABELS.contains(&value.as_str()));
+    let priority = labels.iter().any(|(scope, value)| match scope {
+        Some(scope) => PRIORITY_SCOPES.contains(&scope.as_str()) && PRIORITY_VALUES.contains(&value.as_str()),
+        None => PRIORITY_LABELS.contains(&value.as_str()),
+    });
+    bug && priority
+}
+
+/// Lowercased scope and value of a label: `Priority::High` gives `(Some("priority"), "high")`, `P1` gives `(None, "p1")`
+fn split_label(label: &str) -> (Option<String>, String) {
+    let label = label.trim().to_lowercase();
+    match label.rsplit_once("::") {
+        Some((scope, value)) => (Some(scope.trim().to_string()), value.trim().to_string()),
+        None => (None, label),
+    }
+}
+
+/// People with the most merged MRs and closed issues, then opened issues
+fn top_contributors(created: &[GitLabIssue], closed: &[GitLabIssue], merged: &[GitLabMR]) -> Vec<Contributor> {
+    let mut contributors: Vec<Contributor> = Vec::new();
+    for mr in merged.iter().filter(|mr| !mr.author.username.is_empty()) {
+        contributor(&mut contributors, &mr.author.username).merged_mrs += 1;
+    }
+    for user in closed.iter().filter_map(|issue| issue.closed_by.as_ref()) {
+        contributor(&mut contributors, &user.username).closed_issues += 1;
+    }
+    for issue in created.iter().filter(|issue| !issue.author.username.is_empty()) {
+        contributor(&mut contributors, &issue.author.username).opened_issues += 1;
+    }
+
+    contributors.sort_by(|a, b| {
+        (b.merged_mrs + b.closed_issues).cmp(&(a.merged_mrs + a.closed_issues))
+            .then(b.opened_issues.cmp(&a.opened_issues))
+            .then(a.username.cmp(&b.username))
+    });
+    contributors.truncate(MAX_CONTRIBUTORS);
+    contributors
+}
+
+fn contributor<'a>(contributors: &'a mut Vec<Contributor>, username: &str) -> &'a mut Contributor {
+    let index = match contributors.iter().position(|c| c.username == username) {
+        Some(index) => index,
+        None => {
+            contributors.push(Contributor { username: username.to_string(), ..Contributor::default() });
+            contributors.len() - 1
+        }
+    };
+    &mut contributors[index]
+}
+
+fn markdown_issues(issues: &[ReportIssue]) -> String {
+    if issues.is_empty() {
+        return "None.\n".to_string();
+    }
+    let mut md = String::new();
+    for issue in issues.iter().take(MAX_ITEMS_SHOWN) {
+        let labels = if issue.labels.is_empty() { String::new() } else { format!(" `{}`", issue.labels.join("` `")) };
+        md.push_str(&format!("- [#{}]({}) {}{}{}\n", issue.iid, issue.web_url, issue.title, labels, assignees(issue)));
+    }
+    md.push_str(&more(issues.len(), "- "));
+    md
+}
+
+fn text_issues(issues: &[ReportIssue]) -> String {
+    let mut text = String::new();
+    for issue in issues.iter().take(MAX_ITEMS_SHOWN) {
+        let labels = if issue.labels.is_empty() { String::new() } else { format!(" [{}]", issue.labels.join(", ")) };
+        text.push_str(&format!("  - #{} {}{}{}\n", issue.iid, issue.title, labels, assignees(issue)));
+    }
+    text.push_str(&more(issues.len(), "  "));
+    text
+}
+
+/// " (@alice, @bob)", or nothing when unassigned
+fn assignees(issue: &ReportIssue) -> String {
+    if issue.assignees.is_empty() {
+        return String::new();
+    }
+    let names: Vec<String> = issue.assignees.iter().map(|a| format!("@{}", a)).collect();
+    format!(" ({})", names.join(", "))
+}
+
+/// "... and N more" for a list cut off at `MAX_ITEMS_SHOWN`
+fn more(total: usize, prefix: &str) -> String {
+    if total > MAX_ITEMS_SHOWN {
+        format!("{}... and {} more\n", prefix, total - MAX_ITEMS_SHOWN)
+    } else {
+        String::new()
+    }
+}
+
+/// `YYYY-MM-DD` part of an RFC 3339 timestamp
+fn date(timestamp: &str) -> &str {
+    timestamp.get(..10).unwrap_or(timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(labels: &[&str]) -> GitLabIssue {
+        serde_json::from_value(serde_json::json!({
+            "id": 1, "iid": 1, "title": "t", "state": "opened", "labels": labels,
+        })).unwrap()
+    }
+
+    #[test]
+    fn critical_bugs_need_a_bug_and_a_priority_label() {
+        assert!(is_critical(&issue(&["bug", "P1"])));
+        assert!(is_critical(&issue(&["type::bug", "priority::High"])));
+        assert!(is_critical(&issue(&["Defect", "severity::critical"])));
+        assert!(!is_critical(&issue(&["feature", "critical"])));
+        assert!(!is_critical(&issue(&["bug"])));
+        assert!(!is_critical(&issue(&["bug", "effort::high"])));
+        assert!(!is_critical(&issue(&["bug", "P10"])));
+        assert!(!is_critical(&issue(&["bug", "high-contrast"])));
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::conversation::{Conversation, Turn};
+use crate::util::rand_bytes;
+
+/// A saved REPL conversation under `~/.ken/sessions/<id>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSession {
+    pub id: String,
+    pub profile: String,
+    pub project_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Every turn, including those already summarized out of `conversation`
+    pub turns: Vec<Turn>,
+    /// History as the model sees it, restored on `/resume`
+    pub conversation: Conversation,
+}
+
+impl ChatSession {
+    pub fn new(profile: &str, project_id: Option<&str>) -> Self {
+        let now = chrono::Utc::now();
+        ChatSession {
+            id: new_id(now),
+            profile: profile.to_string(),
+            project_id: project_id.map(str::to_string),
+            created_at: now.to_rfc3339(),
+            updated_at: now.to_rfc3339(),
+            turns: Vec::new(),
+            conversation: Conversation::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    /// Add a finished turn to both the record and the model's history
+    pub fn record(&mut self, project_id: &str, turn: Turn) {
+        self.project_id = Some(project_id.to_string());
+        self.updated_at = turn.timestamp.clone().unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+        self.turns.push(turn.clone());
+        self.conversation.push(turn);
+    }
+
+    /// The first question, used to tell sessions apart in `/sessions`
+    pub fn title(&self) -> &str {
+        self.turns.first().map_or("", |turn| turn.user.lines().next().unwrap_or(""))
+    }
+
+    pub fn sessions_dir() -> Result<PathBuf> {
+        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Failed to get home directory"))?;
+        let dir = home.join(".ken").join("sessions");
+        if !dir.exists() {
+            crate::config::create_private_dir(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    fn session_path(id: &str) -> Result<PathBuf> {
+        Ok(Self::sessions_dir()?.join(file_name(id)?))
+    }
+
+    pub fn load(id: &str) -> Result<Self> {
+        let path = Self::session_path(id)?;
+        if !path.exists() {
+            anyhow::bail!("No session '{}'. Use '/sessions' to list saved sessions.", id);
+        }
+        Self::load_path(&path)
+    }
+
+    fn load_path(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents).with_context(|| format!("Failed to read session {}", path.display()))
+    }
+
+    /// Conversations can quote issue contents, so the file is only readable by the user
+    pub fn save(&mut self) -> Result<()> {
+        self.save_in(&Self::sessions_dir()?)
+    }
+
+    fn save_in(&mut self, dir: &Path) -> Result<()> {
+        let mut path = dir.join(file_name(&self.id)?);
+        // Ids are only probably unique, so take a new one rather than write over another session
+        while path.exists() && !Self::load_path(&path).is_ok_and(|saved| saved.created_at == self.created_at) {
+            self.id = new_id(chrono::Utc::now());
+            path = dir.join(file_name(&self.id)?);
+        }
+        crate::config::write_private(&path, serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    /// All saved sessions, most recently updated first
+    pub fn list() -> Result<Vec<Self>> {
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(Self::sessions_dir()?)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match Self::load_path(&path) {
+                Ok(session) => sessions.push(session),
+                Err(e) => tracing::debug!("Skipping unreadable session {}: {}", path.display(), e),
+            }
+        }
+        sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(sessions)
+    }
+
+    /// Transcript suitable for pasting into an issue or attaching to one
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# Ken session {}\n\n", self.id);
+        md.push_str(&format!("- **Profile:** {}\n", self.profile));
+        md.push_str(&format!("- **Project:** {}\n", self.project_id.as_deref().unwrap_or("none")));
+        md.push_str(&format!("- **Started:** {}\n", display_time(&self.created_at)));
+        md.push_str(&format!("- **Last updated:** {}\n", display_time(&self.updated_at)));
+
+        for turn in &self.turns {
+            let time = turn.timestamp.as_deref().map(display_time).unwrap_or_default();
+            md.push_str(&format!("\n---\n\n### 👤 User {}\n\n{}\n", time, turn.user.trim()));
+
+            if !turn.tool_calls.is_empty() {
+                md.push_str(&format!("\n<details><summary>Tool calls ({})</summary>\n\n", turn.tool_calls.len()));
+                for call in &turn.tool_calls {
+                    let status = if call.failed { " ❌ failed" } else if call.dry_run { " 🧪 dry run" } else { "" };
+                    md.push_str(&format!("- `{}` `{}`{}\n", call.name, call.arguments, status));
+                }
+                md.push_str("\n</details>\n");
+            }
+
+            md.push_str(&format!("\n### 🤖 Ken\n\n{}\n", turn.assistant.trim()));
+        }
+        md
+    }
+}
+
+/// Local start time plus a random suffix, so sessions started in the same second differ
+fn new_id(now: chrono::DateTime<chrono::Utc>) -> String {
+    let suffix: String = rand_bytes::<4>().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}", now.with_timezone(&chrono::Local).format("%Y%m%d-%H%M%S"), suffix)
+}
+
+fn file_name(id: &str) -> Result<String> {
+    if id.is_empty() || id.contains(['/', '\\', '.']) {
+        anyhow::bail!("Invalid session id '{}'", id);
+    }
+    Ok(format!("{}.json", id))
+}
+
+/// Local `YYYY-MM-DD HH:MM` for an RFC 3339 timestamp, or the raw value if it can't be parsed
+pub fn display_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_started_together_get_their_own_ids() {
+        let first = ChatSession::new("default", None);
+        let second = ChatSession::new("default", None);
+        assert_ne!(first.id, second.id);
+        // Ids name files, so they must pass the checks in `file_name`
+        assert!(file_name(&first.id).is_ok());
+    }
+
+    #[test]
+    fn never_saves_over_another_session() {
+        let dir = std::env::temp_dir().join(format!("ken-sessions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut first = ChatSession::new("default", None);
+        first.save_in(&dir).unwrap();
+        
+        let mut second = ChatSession::new("default", None);
+        second.id = first.id.clone();
+        second.save_in(&dir).unwrap();
+        assert_ne!(second.id, first.id);
+        assert_eq!(ChatSession::load_path(&dir.join(file_name(&first.id).unwrap())).unwrap().created_at, first.created_at);
+        
+        // Saving again keeps a session in its own file
+        let id = second.id.clone();
+        second.save_in(&dir).unwrap();
+        assert_eq!(second.id, id);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use crate::context::{HotIssue, ProjectContext};
+
+/// Okapi BM25 term frequency saturation
+const K1: f64 = 1.2;
+/// Okapi BM25 document length normalization
+const B: f64 = 0.75;
+/// Matches scoring below this share of a perfect match are not reported as duplicates
+pub const DUPLICATE_THRESHOLD: f64 = 0.3;
+/// Duplicates listed at most
+pub const MAX_DUPLICATES: usize = 5;
+
+const STOP_WORDS: [&str; 24] = [
+    "the", "and", "for", "with", "when", "from", "that", "this", "not", "are", "was", "but",
+    "can", "has", "have", "after", "into", "should", "does", "issue", "also", "fix", "add", "bug",
+];
+
+/// Lowercase, stemmed words worth comparing, without common filler
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .map(stem)
+        .collect()
+}
+
+/// Strip common English suffixes so "crashes", "crashed" and "crashing" all match "crash"
+fn stem(word: String) -> String {
+    let stemmed = if let Some(base) = word.strip_suffix("ing").or_else(|| word.strip_suffix("ed")) {
+        base
+    } else if let Some(base) = word.strip_suffix("es")
+        && ["s", "x", "z", "ch", "sh"].iter().any(|end| base.ends_with(end)) {
+        base
+    } else {
+        word.strip_suffix('s').filter(|base| !base.ends_with('s')).unwrap_or(&word)
+    };
+    if stemmed.chars().count() >= 3 { stemmed.to_string() } else { word }
+}
+
+/// In-memory BM25 index over issue texts, keyed by issue IID
+pub struct Bm25Index {
+    docs: Vec<(u64, HashMap<String, usize>, usize)>,
+    doc_freq: HashMap<String, usize>,
+    avg_len: f64,
+}
+
+impl Bm25Index {
+    pub fn new(docs: impl IntoIterator<Item = (u64, String)>) -> Self {
+        let mut doc_freq: HashMap<String, usize> = HashMap::new();
+        let docs: Vec<(u64, HashMap<String, usize>, usize)> = docs.into_iter()
+            .map(|(iid, text)| {
+                let words = tokenize(&text);
+                let freq = term_frequencies(&words);
+                for term in freq.keys() {
+                    *doc_freq.entry(term.clone()).or_default() += 1;
+                }
+                (iid, freq, words.len())
+            })
+            .collect();
+        let avg_len = if docs.is_empty() { 0.0 } else { docs.iter().map(|(_, _, len)| *len).sum::<usize>() as f64 / docs.len() as f64 };
+        Self { docs, doc_freq, avg_len }
+    }
+
+    /// Documents sharing terms with `query`, best first. Scores run from 0.0 to 1.0,
+    /// where 1.0 is what a document identical to the query would score.
+    pub fn search(&self, query: &str) -> Vec<(u64, f64)> {
+        let words = tokenize(query);
+        let query_freq = term_frequencies(&words);
+        let idf: HashMap<&str, f64> = query_freq.keys().map(|term| (term.as_str(), self.idf(term))).collect();
+
+        let perfect: f64 = query_freq.iter()
+            .map(|(term, tf)| idf[term.as_str()] * self.saturate(*tf, words.len()))
+            .sum();
+        if perfect <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut results: Vec<(u64, f64)> = self.docs.iter()
+            .filter_map(|(iid, freq, len)| {
+                let score: f64 = query_freq.keys()
+                    .filter_map(|term| freq.get(term).map(|tf| idf[term.as_str()] * self.saturate(*tf, *len)))
+                    .sum();
+                (score > 0.0).then(|| (*iid, (score / perfect).min(1.0)))
+            })
+            .collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results
+    }
+
+    fn idf(&self, term: &str) -> f64 {
+        let n = self.docs.len() as f64;
+        let df = self.doc_freq.get(term).copied().unwrap_or(0) as f64;
+        ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+    }
+
+    fn saturate(&self, tf: usize, len: usize) -> f64 {
+        let tf = tf as f64;
+        let norm = if self.avg_len > 0.0 { len as f64 / self.avg_len } else { 1.0 };
+        tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * norm))
+    }
+}
+
+/// An existing issue that looks like the same report
+#[derive(Debug, Clone)]
+pub struct DuplicateMatch {
+    pub iid: u64,
+    pub title: String,
+    pub state: String,
+    pub score: f64,
+}
+
+/// Open and recently closed issues from the cached context that look like `text`, best first
+pub fn find_duplicates(context: &ProjectContext, text: &str, exclude: Option<u64>) -> Vec<DuplicateMatch> {
+    let issues: Vec<&HotIssue> = context.hot_issues.iter()
+        .chain(&context.recently_closed)
+        .filter(|issue| Some(issue.id as u64) != exclude)
+        .collect();
+    let index = Bm25Index::new(issues.iter().map(|issue| {
+        let text = format!("{} {}", document(&issue.title, issue.description.as_deref()), issue.labels.join(" "));
+        (issue.id as u64, text)
+    }));
+
+    index.search(text).into_iter()
+        .filter(|(_, score)| *score >= DUPLICATE_THRESHOLD)
+        .take(MAX_DUPLICATES)
+        .filter_map(|(iid, score)| {
+            let issue = issues.iter().find(|issue| issue.id as u64 == iid)?;
+            Some(DuplicateMatch { iid, title: issue.title.clone(), state: issue.state.clone(), score })
+        })
+        .collect()
+}
+
+/// Searchable text of an issue. The title is repeated so it outweighs a long description.
+pub fn document(title: &str, description: Option<&str>) -> String {
+    format!("{} {} {}", title, title, description.unwrap_or(""))
+}
+
+fn term_frequencies(words: &[String]) -> HashMap<String, usize> {
+    let mut freq = HashMap::new();
+    for word in words {
+        *freq.entry(word.clone()).or_default() += 1;
+    }
+    freq
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stems_common_suffixes() {
+        let stems: Vec<String> = ["crashes", "crashed", "crashing", "crash"].into_iter().map(|w| stem(w.to_string())).collect();
+        assert!(stems.iter().all(|s| s == "crash"), "{:?}", stems);
+        assert_eq!(stem("uploads".to_string()), "upload");
+        // Too little would be left of these
+        assert_eq!(stem("bed".to_string()), "bed");
+        assert_eq!(stem("class".to_string()), "class");
+        assert_eq!(tokenize("The upload crashed, and it's not a bug"), ["upload", "crash"]);
+    }
+
+    #[test]
+    fn ranks_closer_documents_first() {
+        let index = Bm25Index::new([
+            (1, document("Dark mode colours are off", Some("The sidebar is hard to read"))),
+            (2, document("Upload crashes on large files", Some("Uploading a 2GB file kills the worker"))),
+            (3, document("Upload progress bar stalls", None)),
+        ]);
+        let results = index.search("Uploading large files crashes");
+        let order: Vec<u64> = results.iter().map(|(iid, _)| *iid).collect();
+        assert_eq!(order, [2, 3]);
+        assert!(results.iter().all(|(_, score)| (0.0..=1.0).contains(score)));
+        assert!(results[0].1 > results[1].1);
+    }
+
+    #[test]
+    fn empty_corpus_or_query_matches_nothing() {
+        assert!(Bm25Index::new([]).search("upload crash").is_empty());
+        let index = Bm25Index::new([(1, document("Upload crashes", None))]);
+        assert!(index.search("").is_empty());
+        // Only stop words and short words are left of this one
+        assert!(index.search("the bug is in it").is_empty());
+    }
+
+    #[test]
+    fn finds_duplicates_in_the_context() {
+        let issue = |id: u32, title: &str, state: &str| HotIssue {
+            id,
+            title: title.to_string(),
+            assignee: None,
+            labels: Vec::new(),
+            state: state.to_string(),
+            updated_recently: false,
+            priority: None,
+            description: None,
+        };
+        let context = ProjectContext {
+            hot_issues: vec![issue(1, "Upload crashes on large files", "opened"), issue(2, "Dark mode colours", "opened")],
+            recently_closed: vec![issue(3, "Crash when uploading large files", "closed")],
+            ..ProjectContext::default()
+        };
+        let matches = find_duplicates(&context, "Large file uploads crash", Some(1));
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].iid, matches[0].state.as_str()), (3, "closed"));
+    }
+}